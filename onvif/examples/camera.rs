@@ -1,4 +1,4 @@
-use onvif::soap;
+use onvif::{camera::XAddrPolicy, soap};
 use schema::{self, transport};
 use structopt::StructOpt;
 use tracing::debug;
@@ -107,13 +107,10 @@ impl Clients {
         let services =
             schema::devicemgmt::get_services(&out.devicemgmt, &Default::default()).await?;
         for service in &services.service {
-            let service_url = Url::parse(&service.x_addr).map_err(|e| e.to_string())?;
-            if !service_url.as_str().starts_with(base_uri.as_str()) {
-                return Err(format!(
-                    "Service URI {} is not within base URI {}",
-                    service_url, base_uri
-                ));
-            }
+            // Devices behind NAT advertise their internal address, use the one we reached.
+            let service_url = XAddrPolicy::RewriteHost
+                .resolve(&devicemgmt_uri, &service.x_addr)
+                .map_err(|e| e.to_string())?;
             let svc = Some(
                 soap::client::ClientBuilder::new(&service_url)
                     .credentials(creds.clone())
@@ -222,30 +219,15 @@ async fn get_stream_uris(clients: &Clients) -> Result<(), transport::Error> {
         .ok_or_else(|| transport::Error::Other("Client media is not available".into()))?;
     let profiles = schema::media::get_profiles(media_client, &Default::default()).await?;
     debug!("get_profiles response: {:#?}", &profiles);
-    let requests: Vec<_> = profiles
-        .profiles
-        .iter()
-        .map(|p: &schema::onvif::Profile| schema::media::GetStreamUri {
-            profile_token: schema::onvif::ReferenceToken(p.token.0.clone()),
-            stream_setup: schema::onvif::StreamSetup {
-                stream: schema::onvif::StreamType::RtpUnicast,
-                transport: schema::onvif::Transport {
-                    protocol: schema::onvif::TransportProtocol::Rtsp,
-                    tunnel: vec![],
-                },
-            },
-        })
-        .collect();
-
-    let responses = futures_util::future::try_join_all(
-        requests
-            .iter()
-            .map(|r| schema::media::get_stream_uri(media_client, r)),
+    let uris = onvif::media::get_stream_uris_for_profiles(
+        media_client,
+        &profiles.profiles,
+        schema::onvif::StreamSetup::rtsp_unicast(),
     )
-    .await?;
-    for (p, resp) in profiles.profiles.iter().zip(responses.iter()) {
+    .await;
+    for (p, (_, uri)) in profiles.profiles.iter().zip(uris) {
         println!("token={} name={}", &p.token.0, &p.name.0);
-        println!("    {}", &resp.media_uri.uri);
+        println!("    {}", uri?);
         if let Some(ref v) = p.video_encoder_configuration {
             println!(
                 "    {:?}, {}x{}",
@@ -9,7 +9,21 @@ async fn main() {
     use futures_util::stream::StreamExt;
     const MAX_CONCURRENT_JUMPERS: usize = 100;
 
-    discovery::DiscoveryBuilder::default()
+    let mut builder = discovery::DiscoveryBuilder::default();
+    builder.duration(std::time::Duration::from_secs(3));
+
+    // E.g. `LISTEN_ADDRESS=fe80::1 SCOPE_ID=2` to discover over IPv6.
+    match std::env::var("LISTEN_ADDRESS") {
+        Ok(listen_address) => {
+            builder.listen_address(listen_address.parse().expect("Invalid LISTEN_ADDRESS"))
+        }
+        Err(_) => builder.listen_all_interfaces(),
+    };
+    if let Ok(scope_id) = std::env::var("SCOPE_ID") {
+        builder.scope_id(scope_id.parse().expect("Invalid SCOPE_ID"));
+    }
+
+    builder
         .run()
         .await
         .unwrap()
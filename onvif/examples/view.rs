@@ -1,14 +1,19 @@
 use std::str::FromStr;
 
 use onvif::{
+    camera::{self, Camera},
     discovery::{self, Device},
+    media::VideoCodec,
     soap,
 };
-use schema::{self, transport};
+use schema::{onvif::StreamSetup, transport::Error};
 use structopt::StructOpt;
 use tracing::debug;
 use url::Url;
 
+/// Short enough for unresponsive cameras not to hold the concurrency slots for long.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
 #[derive(StructOpt, Clone, Debug)]
 #[structopt(name = "view", about = "Factbird View camera discovery tool")]
 struct Args {
@@ -18,8 +23,43 @@ struct Args {
     #[structopt(global = true, long, requires = "username")]
     password: Option<String>,
 
-    #[structopt(global = true, long, default_value = "192.168.0.1")]
-    listen_addr: String,
+    /// Discovers on all network interfaces if not set.
+    #[structopt(global = true, long)]
+    listen_addr: Option<String>,
+
+    /// Transport of the streams: `rtsp`, `multicast` or `http` (RTSP tunneled in HTTP).
+    #[structopt(global = true, long, default_value = "rtsp")]
+    transport: StreamTransport,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StreamTransport {
+    Rtsp,
+    Multicast,
+    Http,
+}
+
+impl StreamTransport {
+    fn stream_setup(self) -> StreamSetup {
+        match self {
+            StreamTransport::Rtsp => StreamSetup::rtsp_unicast(),
+            StreamTransport::Multicast => StreamSetup::rtp_multicast(),
+            StreamTransport::Http => StreamSetup::rtsp_over_http(),
+        }
+    }
+}
+
+impl FromStr for StreamTransport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rtsp" => Ok(StreamTransport::Rtsp),
+            "multicast" => Ok(StreamTransport::Multicast),
+            "http" => Ok(StreamTransport::Http),
+            _ => Err(format!("unknown transport {:?}", s)),
+        }
+    }
 }
 
 struct ClientArgs {
@@ -32,80 +72,34 @@ struct ClientArgs {
     service_path: String,
 }
 
-struct Clients {
-    devicemgmt: soap::client::Client,
-    event: Option<soap::client::Client>,
-    deviceio: Option<soap::client::Client>,
-    media: Option<soap::client::Client>,
-    media2: Option<soap::client::Client>,
-    imaging: Option<soap::client::Client>,
-    ptz: Option<soap::client::Client>,
-    analytics: Option<soap::client::Client>,
-}
-
-impl Clients {
-    async fn new(args: &ClientArgs) -> Result<Self, String> {
-        let creds = match (args.username.as_ref(), args.password.as_ref()) {
-            (Some(username), Some(password)) => Some(soap::client::Credentials {
-                username: username.clone(),
-                password: password.clone(),
-            }),
-            (None, None) => None,
-            _ => panic!("username and password must be specified together"),
-        };
-        let devicemgmt_uri = args.uri.join(&args.service_path).unwrap();
-        let mut out = Self {
-            devicemgmt: soap::client::ClientBuilder::new(&devicemgmt_uri)
-                .credentials(creds.clone())
-                .build(),
-            imaging: None,
-            ptz: None,
-            event: None,
-            deviceio: None,
-            media: None,
-            media2: None,
-            analytics: None,
-        };
-        let services =
-            schema::devicemgmt::get_services(&out.devicemgmt, &Default::default()).await?;
-        for service in &services.service {
-            let service_url = Url::parse(&service.x_addr).map_err(|e| e.to_string())?;
-            if !service_url.as_str().starts_with(args.uri.as_str()) {
-                return Err(format!(
-                    "Service URI {} is not within base URI {}",
-                    service_url, args.uri
-                ));
-            }
-            let svc = Some(
-                soap::client::ClientBuilder::new(&service_url)
-                    .credentials(creds.clone())
-                    .build(),
-            );
-            match service.namespace.as_str() {
-                "http://www.onvif.org/ver10/device/wsdl" => {
-                    if service_url != devicemgmt_uri {
-                        return Err(format!(
-                            "advertised device mgmt uri {} not expected {}",
-                            service_url, devicemgmt_uri
-                        ));
-                    }
-                }
-                "http://www.onvif.org/ver10/events/wsdl" => out.event = svc,
-                "http://www.onvif.org/ver10/deviceIO/wsdl" => out.deviceio = svc,
-                "http://www.onvif.org/ver10/media/wsdl" => out.media = svc,
-                "http://www.onvif.org/ver20/media/wsdl" => out.media2 = svc,
-                "http://www.onvif.org/ver20/imaging/wsdl" => out.imaging = svc,
-                "http://www.onvif.org/ver20/ptz/wsdl" => out.ptz = svc,
-                "http://www.onvif.org/ver20/analytics/wsdl" => out.analytics = svc,
-                _ => debug!("unknown service: {:?}", service),
-            }
-        }
-        Ok(out)
-    }
+async fn connect(args: &ClientArgs) -> Result<Camera, camera::Error> {
+    let creds = match (args.username.as_ref(), args.password.as_ref()) {
+        (Some(username), Some(password)) => Some(soap::client::Credentials {
+            username: username.clone(),
+            password: password.clone(),
+        }),
+        (None, None) => None,
+        _ => panic!("username and password must be specified together"),
+    };
+    // One connection pool for the clients of all services.
+    let http_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .use_rustls_tls()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .map_err(|e| Error::Other(e.to_string()))?;
+    let devicemgmt_uri = args.uri.join(&args.service_path).unwrap();
+    let devicemgmt = soap::client::ClientBuilder::new(&devicemgmt_uri)
+        .credentials(creds)
+        .timeout(REQUEST_TIMEOUT)
+        .http_client(http_client)
+        .build();
+
+    Camera::connect_with(devicemgmt).await
 }
 
 pub struct VideoSpec {
-    encoding: String,
+    codec: Option<VideoCodec>,
     width: i32,
     height: i32,
 }
@@ -113,47 +107,45 @@ pub struct VideoSpec {
 pub struct StreamSpec {
     name: String,
     media_uri: String,
+    /// Stream type and protocol the URI is for, e.g. `RtpUnicast/Rtsp`.
+    transport: String,
     video: VideoSpec,
 }
 
-async fn get_stream_uris(clients: &Clients) -> Result<Vec<StreamSpec>, transport::Error> {
-    let media_client = clients
-        .media
-        .as_ref()
-        .ok_or_else(|| transport::Error::Other("Client media is not available".into()))?;
-    let profiles = schema::media::get_profiles(media_client, &Default::default()).await?;
+/// Streams of the profiles with a video encoder, for `stream_setup`. Profiles whose stream the
+/// device refuses to set up this way are skipped.
+async fn get_stream_uris(
+    camera: &Camera,
+    stream_setup: StreamSetup,
+) -> Result<Vec<StreamSpec>, camera::Error> {
+    let transport = format!("{:?}/{:?}", stream_setup.stream, stream_setup.transport.protocol);
+    let media_client = camera.media()?;
+    let profiles = onvif::media::get_profiles(media_client).await?;
     debug!("get_profiles response: {:#?}", &profiles);
-    let requests: Vec<_> = profiles
-        .profiles
-        .iter()
-        .map(|p: &schema::onvif::Profile| schema::media::GetStreamUri {
-            profile_token: schema::onvif::ReferenceToken(p.token.0.clone()),
-            stream_setup: schema::onvif::StreamSetup {
-                stream: schema::onvif::StreamType::RtpUnicast,
-                transport: schema::onvif::Transport {
-                    protocol: schema::onvif::TransportProtocol::Rtsp,
-                    tunnel: vec![],
-                },
-            },
-        })
-        .collect();
-
-    let responses = futures_util::future::try_join_all(
-        requests
-            .iter()
-            .map(|r| schema::media::get_stream_uri(media_client, r)),
-    )
-    .await?;
+    for error in &profiles.errors {
+        debug!("Skipping malformed profile: {}", error);
+    }
+    let uris =
+        onvif::media::get_stream_uris_for_profiles(media_client, &profiles.profiles, stream_setup)
+            .await;
 
     let mut streams = vec![];
 
-    for (p, resp) in profiles.profiles.iter().zip(responses.iter()) {
+    for (p, (_, uri)) in profiles.profiles.iter().zip(uris) {
+        let uri = match uri {
+            Ok(uri) => uri,
+            Err(e) => {
+                debug!("Skipping profile {}: no {} stream: {}", p.name.0, transport, e);
+                continue;
+            }
+        };
         if let Some(ref v) = p.video_encoder_configuration {
             streams.push(StreamSpec {
                 name: p.name.0.clone(),
-                media_uri: resp.media_uri.uri.clone(),
+                media_uri: uri,
+                transport: transport.clone(),
                 video: VideoSpec {
-                    encoding: format!("{:?}", v.encoding),
+                    codec: VideoCodec::from_encoding(&v.encoding),
                     width: v.resolution.width,
                     height: v.resolution.height,
                 },
@@ -169,58 +161,90 @@ async fn main() {
     const MAX_CONCURRENT_JUMPERS: usize = 100;
     env_logger::init();
 
-    let listen_addr = std::net::Ipv4Addr::from_str(Args::from_args().listen_addr.as_str()).unwrap();
+    let listen_addr = Args::from_args()
+        .listen_addr
+        .map(|addr| std::net::IpAddr::from_str(&addr).unwrap());
+
+    let mut builder = discovery::DiscoveryBuilder::default();
+    match listen_addr {
+        Some(listen_addr) => builder.listen_address(listen_addr),
+        None => builder.listen_all_interfaces(),
+    };
 
-    if let Ok(devices_stream) = discovery::DiscoveryBuilder::default()
-        .listen_address(listen_addr.into())
-        .run()
-        .await
-    {
+    if let Ok(devices_stream) = builder.run().await {
         devices_stream
             .for_each_concurrent(MAX_CONCURRENT_JUMPERS, |addr: Device| async move {
                 let args = Args::from_args();
+                debug!(
+                    "Found {} ({})",
+                    addr.name.as_deref().unwrap_or("unnamed device"),
+                    addr.location.as_deref().unwrap_or("unknown location")
+                );
                 let service_path = String::from("onvif/device_service");
 
-                let uri = addr
-                    .urls
-                    .into_iter()
-                    .find(|u| {
-                        u.scheme() == "https"
-                            && u.host_str()
-                                .map(|h| {
-                                    let host_ip = std::net::Ipv4Addr::from_str(h).unwrap();
-                                    host_ip.octets()[..3].eq(&listen_addr.octets()[..3])
-                                })
-                                .unwrap_or_default()
-                    })
-                    .expect("device does not have any https urls?");
-
-                let uri = uri
+                // Only the addresses in the network of the interface the device was heard on are
+                // reachable.
+                let Some(interface) = addr.interface.or(listen_addr) else {
+                    return;
+                };
+                let Some(uri) = addr.best_service_url(true, interface) else {
+                    debug!("No URL of {:?} is reachable from {}", addr.urls, interface);
+                    return;
+                };
+
+                let base = uri
                     .as_str()
                     .strip_suffix(service_path.as_str())
                     .unwrap_or_else(|| uri.as_str());
+                let Ok(base) = Url::from_str(base) else {
+                    return;
+                };
 
-                let Ok(clients) = Clients::new(&ClientArgs {
+                let camera = match connect(&ClientArgs {
                     username: args.username.clone(),
                     password: args.password.clone(),
-                    uri: Url::from_str(uri).unwrap(),
+                    uri: base,
                     service_path,
                 })
-                .await else {
-                    return;
+                .await
+                {
+                    Ok(camera) => camera,
+                    Err(camera::Error::Transport(Error::Unauthorized(e))) => {
+                        eprintln!(
+                            "{} rejected the credentials ({}), run again with the right \
+                             --username and --password",
+                            uri, e
+                        );
+                        return;
+                    }
+                    Err(e @ camera::Error::Transport(Error::Connection(_) | Error::Timeout(_))) => {
+                        debug!("Skipping unreachable {}: {}", uri, e);
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to connect to {}: {}", uri, e);
+                        return;
+                    }
                 };
 
-                if let Ok(streams) = get_stream_uris(&clients).await {
+                let creds = args.username.clone().zip(args.password.clone()).map(
+                    |(username, password)| soap::client::Credentials { username, password },
+                );
+
+                if let Ok(streams) = get_stream_uris(&camera, args.transport.stream_setup()).await {
                     for stream in streams
                         .iter()
-                        .filter(|s| s.video.encoding.to_ascii_lowercase().as_str() == "h264")
+                        .filter(|s| s.video.codec == Some(VideoCodec::H264))
                     {
-                        println!(
-                            "rtsp://{}:{}@{}",
-                            args.username.clone().unwrap(),
-                            args.password.clone().unwrap(),
-                            stream.media_uri.strip_prefix("rtsp://").unwrap()
-                        );
+                        debug!("Profile {}: {} stream", stream.name, stream.transport);
+                        let Some(creds) = &creds else {
+                            println!("{}", stream.media_uri);
+                            continue;
+                        };
+                        match onvif::media::authenticated_rtsp_uri(&stream.media_uri, creds) {
+                            Ok(uri) => println!("{}", uri),
+                            Err(e) => debug!("Skipping {}: {}", stream.media_uri, e),
+                        }
                     }
                 }
             })
@@ -0,0 +1,110 @@
+use crate::utils::fault::is_not_supported;
+use schema::{
+    analytics,
+    onvif::ReferenceToken,
+    transport::{self, Transport},
+};
+
+/// Analytics module a video analytics configuration can run, as described by
+/// `GetSupportedAnalyticsModules`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyticsModule {
+    /// Type of the module, e.g. `tt:CellMotionEngine`.
+    pub name: String,
+    /// Whether the module can't be removed from the configuration.
+    pub fixed: bool,
+    /// Names of the simple parameters of the module.
+    pub parameters: Vec<String>,
+}
+
+/// Lists the analytics modules supported by the video analytics configuration.
+///
+/// Devices which advertise the analytics service without implementing modules respond with an
+/// empty list, or with a `ter:ActionNotSupported` fault. Both give an empty list.
+pub async fn get_supported_analytics_modules<T: Transport>(
+    transport: &T,
+    configuration_token: &str,
+) -> Result<Vec<AnalyticsModule>, transport::Error> {
+    let modules = match analytics::get_supported_analytics_modules(
+        transport,
+        &analytics::GetSupportedAnalyticsModules {
+            configuration_token: ReferenceToken(configuration_token.to_string()),
+        },
+    )
+    .await
+    {
+        Ok(response) => response.supported_analytics_modules,
+        Err(e) if is_not_supported(&e) => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    Ok(modules
+        .analytics_module_description
+        .iter()
+        .map(|module| AnalyticsModule {
+            name: module.name.clone(),
+            fixed: module.fixed.unwrap_or(false),
+            parameters: module
+                .parameters
+                .simple_item_description
+                .iter()
+                .map(|item| item.name.clone())
+                .collect(),
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn supported_analytics_modules() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tan:GetSupportedAnalyticsModulesResponse
+                xmlns:tan="http://www.onvif.org/ver20/analytics/wsdl"
+                xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tan:SupportedAnalyticsModules>
+                <tt:AnalyticsModuleDescription Name="tt:CellMotionEngine" fixed="true">
+                    <tt:Parameters>
+                        <tt:SimpleItemDescription Name="Sensitivity" Type="xs:integer"/>
+                    </tt:Parameters>
+                </tt:AnalyticsModuleDescription>
+            </tan:SupportedAnalyticsModules>
+        </tan:GetSupportedAnalyticsModulesResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_supported_analytics_modules(&transport, "VideoAnalytics_1")
+            .await
+            .unwrap(),
+        [AnalyticsModule {
+            name: "tt:CellMotionEngine".to_string(),
+            fixed: true,
+            parameters: vec!["Sensitivity".to_string()],
+        }]
+    );
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tan:GetSupportedAnalyticsModulesResponse
+                xmlns:tan="http://www.onvif.org/ver20/analytics/wsdl">
+            <tan:SupportedAnalyticsModules/>
+        </tan:GetSupportedAnalyticsModulesResponse>
+        "#,
+    );
+    assert!(
+        get_supported_analytics_modules(&transport, "VideoAnalytics_1")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let transport = RecordingTransport::fault("env:Receiver/ter:ActionNotSupported");
+    assert!(
+        get_supported_analytics_modules(&transport, "VideoAnalytics_1")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,817 @@
+use crate::{
+    capabilities::{self, Capabilities},
+    devicemgmt::DeviceInformation,
+    event::{self, Event, EventStreamOptions},
+    media::{self, VideoCodec},
+    media2::{self, StreamProtocol},
+    soap::client::{Client, ClientBuilder, Credentials, Services},
+    utils::{date_time::to_chrono, fault::is_not_supported},
+};
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::TryStreamExt;
+use schema::{devicemgmt, onvif as tt, transport};
+use std::{sync::Arc, time::Duration};
+use thiserror::Error;
+use tracing::debug;
+use url::Url;
+
+/// Clock difference above which a rejected UsernameToken is attributed to the camera clock rather
+/// than to wrong credentials.
+pub const CLOCK_SKEW_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
+    /// The device rejected the credentials, but its clock is too far off for UsernameToken auth
+    /// to work. `offset` is the device time minus the local time.
+    #[error("Clock skew between the device and the client is too large: {offset}")]
+    ClockSkew { offset: chrono::Duration },
+
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+
+    /// A service is advertised on another origin than the device management service, with
+    /// [`XAddrPolicy::RequireSameOrigin`].
+    #[error("Service URI {uri} is not on the origin of {base}")]
+    ForeignServiceUri { uri: Url, base: Url },
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] transport::Error),
+}
+
+/// Stream of a media profile, see [`Camera::stream_uris`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileStream {
+    pub profile_token: String,
+    pub profile_name: String,
+    /// Codec of the video encoder of the profile, if it has one the crate knows.
+    pub codec: Option<VideoCodec>,
+    /// Video resolution as `(width, height)`, if the profile has a video encoder.
+    pub resolution: Option<(u32, u32)>,
+    /// RTSP URI of the stream.
+    pub uri: String,
+}
+
+/// Image downloaded by [`Camera::fetch_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// `Content-Type` of the image, usually `image/jpeg` but some devices send PNG.
+    pub content_type: Option<String>,
+    pub data: Bytes,
+}
+
+/// How [`Camera`] reconciles the service addresses (XAddrs) advertised by the device with the URI
+/// of the device management service it was reached on.
+///
+/// Relative XAddrs are always resolved against that URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddrPolicy {
+    /// Uses absolute XAddrs as they are, even on other hosts, ports or paths.
+    AsAdvertised,
+    /// Replaces the host of the XAddrs by the one of the device management service, for devices
+    /// behind NAT or port forwarding which advertise their internal address. The advertised ports
+    /// are kept.
+    RewriteHost,
+    /// Rejects the XAddrs with another scheme, host or port than the device management service
+    /// with [`Error::ForeignServiceUri`].
+    RequireSameOrigin,
+}
+
+impl Default for XAddrPolicy {
+    fn default() -> Self {
+        Self::AsAdvertised
+    }
+}
+
+impl XAddrPolicy {
+    /// URL of the service at `x_addr` for the device management service at `base`.
+    pub fn resolve(self, base: &Url, x_addr: &str) -> Result<Url, Error> {
+        let mut uri = base
+            .join(x_addr.trim())
+            .map_err(|e| transport::Error::deserialization(x_addr, e))?;
+
+        match self {
+            Self::AsAdvertised => {}
+            Self::RewriteHost => {
+                if uri.host_str() != base.host_str() {
+                    uri.set_host(base.host_str())
+                        .map_err(|e| transport::Error::deserialization(x_addr, e))?;
+                }
+            }
+            Self::RequireSameOrigin => {
+                if uri.origin() != base.origin() {
+                    return Err(Error::ForeignServiceUri {
+                        uri,
+                        base: base.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(uri)
+    }
+}
+
+/// Interval between the requests [`Camera::reboot_and_wait`] sends to check if the camera is
+/// online.
+pub const REBOOT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// High-level handle to an ONVIF device.
+#[derive(Clone)]
+pub struct Camera {
+    devicemgmt: Client,
+    event: Option<Client>,
+    deviceio: Option<Client>,
+    media: Option<Client>,
+    media2: Option<Client>,
+    imaging: Option<Client>,
+    ptz: Option<Client>,
+    analytics: Option<Client>,
+    x_addr_policy: XAddrPolicy,
+}
+
+impl Camera {
+    /// Creates a camera from a client for the device management service
+    /// (typically `http://<host>/onvif/device_service`).
+    ///
+    /// The other services are unknown, use [`Camera::connect`] or [`Camera::connect_with`] to
+    /// have them.
+    pub fn new(devicemgmt: Client) -> Self {
+        Self {
+            devicemgmt,
+            event: None,
+            deviceio: None,
+            media: None,
+            media2: None,
+            imaging: None,
+            ptz: None,
+            analytics: None,
+            x_addr_policy: XAddrPolicy::default(),
+        }
+    }
+
+    /// Sets how the service addresses advertised by the device are handled, see [`XAddrPolicy`].
+    ///
+    /// It applies from the next [`Camera::refresh_services`]:
+    ///
+    /// ```no_run
+    /// # use onvif::{camera::Error, soap::client::Client};
+    /// # async fn example(client: Client) -> Result<(), Error> {
+    /// use onvif::camera::{Camera, XAddrPolicy};
+    ///
+    /// let mut camera = Camera::new(client).with_x_addr_policy(XAddrPolicy::RewriteHost);
+    /// camera.refresh_services().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_x_addr_policy(mut self, policy: XAddrPolicy) -> Self {
+        self.x_addr_policy = policy;
+        self
+    }
+
+    /// Connects to the device management service at `uri` (typically
+    /// `http://<host>/onvif/device_service`) and creates clients for the services the device
+    /// advertises.
+    pub async fn connect(uri: &Url, credentials: Option<Credentials>) -> Result<Self, Error> {
+        Self::connect_with(ClientBuilder::new(uri).credentials(credentials).build()).await
+    }
+
+    /// Like [`Camera::connect`], with a client for the device management service configured by
+    /// the caller. The clients of the other services have the same settings.
+    ///
+    /// Devices which don't implement `GetServices` (they respond with a fault like
+    /// `ter:ActionNotSupported`) are asked for their capabilities instead, other errors are
+    /// returned as is. If the client has a
+    /// [services TTL](crate::soap::client::ClientBuilder::services_ttl), the services of the device
+    /// are reused for that long.
+    pub async fn connect_with(devicemgmt: Client) -> Result<Self, Error> {
+        let mut camera = Self::new(devicemgmt);
+        camera.resolve_services(true).await?;
+        Ok(camera)
+    }
+
+    /// Fetches the services of the device again, e.g. after its configuration has changed, and
+    /// updates the cache if the client has a
+    /// [services cache](crate::soap::client::ClientBuilder::services_cache).
+    pub async fn refresh_services(&mut self) -> Result<(), Error> {
+        self.resolve_services(false).await
+    }
+
+    async fn resolve_services(&mut self, use_cache: bool) -> Result<(), Error> {
+        let cached = if use_cache {
+            self.devicemgmt.cached_services()
+        } else {
+            None
+        };
+
+        let services = match cached {
+            Some(services) => services,
+            None => match devicemgmt::get_services(&self.devicemgmt, &Default::default()).await {
+                Ok(response) => {
+                    let services: Services = Arc::new(
+                        response
+                            .service
+                            .into_iter()
+                            .map(|service| (service.namespace, service.x_addr))
+                            .collect(),
+                    );
+
+                    self.devicemgmt.cache_services(services.clone());
+
+                    services
+                }
+                Err(e) if is_not_supported(&e) => {
+                    debug!(
+                        "Failed to get services: {}. Falling back to capabilities",
+                        e
+                    );
+                    return self.resolve_capabilities().await;
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        *self = Self::new(self.devicemgmt.clone()).with_x_addr_policy(self.x_addr_policy);
+
+        // Only the addresses of the services which are kept are resolved, so that an invalid or
+        // rejected one for another service doesn't prevent the connection.
+        for (namespace, x_addr) in services.iter() {
+            let service = match namespace.as_str() {
+                "http://www.onvif.org/ver10/device/wsdl" => continue,
+                "http://www.onvif.org/ver10/events/wsdl" => &mut self.event,
+                "http://www.onvif.org/ver10/deviceIO/wsdl" => &mut self.deviceio,
+                "http://www.onvif.org/ver10/media/wsdl" => &mut self.media,
+                "http://www.onvif.org/ver20/media/wsdl" => &mut self.media2,
+                "http://www.onvif.org/ver20/imaging/wsdl" => &mut self.imaging,
+                "http://www.onvif.org/ver20/ptz/wsdl" => &mut self.ptz,
+                "http://www.onvif.org/ver20/analytics/wsdl" => &mut self.analytics,
+                _ => {
+                    debug!("Unknown service: {} at {}", namespace, x_addr);
+                    continue;
+                }
+            };
+
+            *service = Some(service_client(
+                &self.devicemgmt,
+                self.x_addr_policy,
+                x_addr,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    async fn resolve_capabilities(&mut self) -> Result<(), Error> {
+        let capabilities = capabilities::get_capabilities(&self.devicemgmt).await?;
+
+        *self = Self::new(self.devicemgmt.clone()).with_x_addr_policy(self.x_addr_policy);
+
+        if let Some(events) = &capabilities.events {
+            self.event = Some(self.service_client(&events.x_addr)?);
+        }
+        if let Some(media) = &capabilities.media {
+            self.media = Some(self.service_client(&media.x_addr)?);
+        }
+        if let Some(imaging) = &capabilities.imaging {
+            self.imaging = Some(self.service_client(&imaging.x_addr)?);
+        }
+        if let Some(ptz) = &capabilities.ptz {
+            self.ptz = Some(self.service_client(&ptz.x_addr)?);
+        }
+        if let Some(analytics) = &capabilities.analytics {
+            self.analytics = Some(self.service_client(&analytics.x_addr)?);
+        }
+
+        Ok(())
+    }
+
+    /// Client for the service at `x_addr`, see [`service_client`].
+    fn service_client(&self, x_addr: &str) -> Result<Client, Error> {
+        service_client(&self.devicemgmt, self.x_addr_policy, x_addr)
+    }
+
+    /// Client for the device management service.
+    pub fn devicemgmt(&self) -> &Client {
+        &self.devicemgmt
+    }
+
+    /// Client for the device IO service.
+    pub fn deviceio(&self) -> Result<&Client, Error> {
+        service(&self.deviceio, "Device IO")
+    }
+
+    /// Client for the media service.
+    pub fn media(&self) -> Result<&Client, Error> {
+        service(&self.media, "Media")
+    }
+
+    /// Client for the media2 service.
+    pub fn media2(&self) -> Result<&Client, Error> {
+        service(&self.media2, "Media2")
+    }
+
+    /// Client for the imaging service.
+    pub fn imaging(&self) -> Result<&Client, Error> {
+        service(&self.imaging, "Imaging")
+    }
+
+    /// Client for the PTZ service.
+    pub fn ptz(&self) -> Result<&Client, Error> {
+        service(&self.ptz, "PTZ")
+    }
+
+    /// Client for the analytics service.
+    pub fn analytics(&self) -> Result<&Client, Error> {
+        service(&self.analytics, "Analytics")
+    }
+
+    /// URI of a JPEG snapshot of the media profile.
+    ///
+    /// Returns [`Error::Unsupported`] if the device has no media service or doesn't support
+    /// snapshots.
+    pub async fn snapshot_uri(&self, profile_token: &str) -> Result<String, Error> {
+        media::get_snapshot_uri(self.media()?, profile_token)
+            .await?
+            .ok_or_else(|| Error::Unsupported("Snapshot URI".to_string()))
+    }
+
+    /// Downloads a snapshot of the media profile, with the credentials of the camera.
+    ///
+    /// Returns [`Error::Unsupported`] if the device has no media service or doesn't support
+    /// snapshots.
+    pub async fn fetch_snapshot(&self, profile_token: &str) -> Result<Snapshot, Error> {
+        let uri = self.snapshot_uri(profile_token).await?;
+        let uri = Url::parse(&uri).map_err(|e| {
+            transport::Error::Protocol(format!("Invalid snapshot URI {:?}: {}", uri, e))
+        })?;
+
+        let (headers, data) = self.media()?.get_authenticated(&uri).await?;
+
+        Ok(Snapshot {
+            content_type: headers
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+            data,
+        })
+    }
+
+    /// Lists the profiles of the device along with their RTSP stream URIs.
+    ///
+    /// The Media2 service is used if the device has it, as some newer devices implement only
+    /// that one, and media1 otherwise (or if Media2 fails). Profiles which media1 can't
+    /// deserialize are skipped, as are the ones the device fails to return a URI for, unless it
+    /// fails for all of them.
+    pub async fn stream_uris(&self) -> Result<Vec<ProfileStream>, Error> {
+        if let Some(media2) = &self.media2 {
+            match media2_stream_uris(media2).await {
+                Ok(streams) => return Ok(streams),
+                Err(e) if self.media.is_some() => {
+                    debug!(
+                        "Failed to get Media2 stream URIs: {}. Falling back to media1",
+                        e
+                    )
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let media = self.media()?;
+        let profiles = media::get_profiles(media).await?;
+
+        for error in &profiles.errors {
+            debug!("Skipping malformed profile: {}", error);
+        }
+
+        let uris = media::get_stream_uris_for_profiles(
+            media,
+            &profiles.profiles,
+            tt::StreamSetup::rtsp_unicast(),
+        )
+        .await;
+
+        let streams = profiles
+            .profiles
+            .iter()
+            .zip(uris)
+            .map(|(profile, (token, uri))| {
+                let resolution = profile
+                    .video_encoder_configuration
+                    .as_ref()
+                    .map(|configuration| &configuration.resolution)
+                    .map(|r| (r.width.max(0) as u32, r.height.max(0) as u32));
+
+                let stream = uri.map(|uri| ProfileStream {
+                    profile_token: profile.token.0.clone(),
+                    profile_name: profile.name.0.clone(),
+                    codec: VideoCodec::of_profile(profile),
+                    resolution,
+                    uri,
+                });
+
+                (token, stream)
+            });
+
+        Ok(resolved_streams(streams)?)
+    }
+
+    /// Manufacturer, model, firmware version and serial number of the device.
+    ///
+    /// If the device rejects the credentials, the request is retried without them, as many
+    /// devices serve it anonymously. This helps to tell which device rejects the credentials.
+    pub async fn device_information(&self) -> Result<DeviceInformation, Error> {
+        match crate::devicemgmt::get_device_information(&self.devicemgmt).await {
+            Err(e @ transport::Error::Unauthorized(_)) => {
+                crate::devicemgmt::get_device_information(&self.devicemgmt.with_credentials(None))
+                    .await
+                    .map_err(|_| e.into())
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Reboots the camera and returns the message it responds with, if it does before dropping
+    /// the connection. See [`devicemgmt::system_reboot`](crate::devicemgmt::system_reboot).
+    pub async fn reboot(&self) -> Result<Option<String>, Error> {
+        Ok(crate::devicemgmt::system_reboot(&self.devicemgmt).await?)
+    }
+
+    /// Like [`Camera::reboot`], and then waits until the camera goes offline and answers again,
+    /// for at most `timeout`.
+    ///
+    /// The camera is checked every [`REBOOT_POLL_INTERVAL`] with `GetSystemDateAndTime`, which
+    /// doesn't require authentication. Returns [`transport::Error::Timeout`] if it's not back
+    /// online in time.
+    pub async fn reboot_and_wait(&self, timeout: Duration) -> Result<Option<String>, Error> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let message = self.reboot().await?;
+
+        let anonymous = self.devicemgmt.with_credentials(None);
+        let request = devicemgmt::GetSystemDateAndTime::default();
+
+        // The camera may still answer for a while after accepting the request.
+        let mut went_offline = false;
+
+        while tokio::time::Instant::now() < deadline {
+            let is_online = matches!(
+                tokio::time::timeout_at(
+                    deadline,
+                    devicemgmt::get_system_date_and_time(&anonymous, &request)
+                )
+                .await,
+                Ok(Ok(_))
+            );
+
+            match (is_online, went_offline) {
+                (true, true) => return Ok(message),
+                (false, false) => {
+                    debug!("{} went offline", self.devicemgmt.uri());
+                    went_offline = true;
+                }
+                _ => {}
+            }
+
+            tokio::time::sleep_until(
+                deadline.min(tokio::time::Instant::now() + REBOOT_POLL_INTERVAL),
+            )
+            .await;
+        }
+
+        Err(
+            transport::Error::Timeout(format!("Device isn't back online after {:?}", timeout))
+                .into(),
+        )
+    }
+
+    /// Checks that the device accepts the client's credentials.
+    ///
+    /// Performs a single read-only request which requires authentication: `GetProfiles` if the
+    /// camera has a media service, which any user level may send, and `GetUsers` otherwise. Returns
+    /// [`Error::InvalidCredentials`] if the device rejects the credentials, [`Error::ClockSkew`] if
+    /// it rejects them while its clock differs from the local one by more than
+    /// [`CLOCK_SKEW_THRESHOLD`], and [`Error::Transport`] for any other failure (device is
+    /// offline, etc).
+    pub async fn verify_credentials(&self) -> Result<(), Error> {
+        // Not `GetDeviceInformation`, as many devices serve it anonymously. `GetProfiles` has the
+        // `READ_MEDIA` access class and `GetUsers` the `READ_SYSTEM_SECRET` one, which anonymous
+        // users don't have.
+        let result = match &self.media {
+            Some(media) => media::get_profiles(media).await.map(drop),
+            None => devicemgmt::get_users(&self.devicemgmt, &Default::default())
+                .await
+                .map(drop),
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(transport::Error::Unauthorized(_)) => Err(self.diagnose_unauthorized().await),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Difference between the device clock and the local clock (device time minus local time).
+    ///
+    /// Returns `None` if the device doesn't report its UTC time.
+    pub async fn clock_offset(&self) -> Result<Option<chrono::Duration>, Error> {
+        // `GetSystemDateAndTime` doesn't require authentication. It's sent without credentials,
+        // as devices which reject them because of the clock would reject this request too.
+        let response = devicemgmt::get_system_date_and_time(
+            &self.devicemgmt.with_credentials(None),
+            &Default::default(),
+        )
+        .await?;
+        let now = chrono::Utc::now();
+
+        Ok(response
+            .system_date_and_time
+            .utc_date_time
+            .as_ref()
+            .and_then(to_chrono)
+            .map(|device_time| device_time - now))
+    }
+
+    /// Client for the event service.
+    ///
+    /// Unless the camera is made by [`Camera::connect`], the address of the service is taken from
+    /// the device capabilities.
+    pub async fn events(&self) -> Result<Client, Error> {
+        if let Some(event) = &self.event {
+            return Ok(event.clone());
+        }
+
+        let events = self
+            .capabilities()
+            .await?
+            .events
+            .ok_or_else(|| Error::Unsupported("Event service".to_string()))?;
+
+        self.service_client(&events.x_addr)
+    }
+
+    /// Services and features of the device reported by `GetCapabilities`.
+    pub async fn capabilities(&self) -> Result<Capabilities, Error> {
+        Ok(capabilities::get_capabilities(&self.devicemgmt).await?)
+    }
+
+    /// Subscribes to the events of the device and yields them as they come, see
+    /// [`event::event_stream`].
+    pub fn event_stream(
+        &self,
+        options: EventStreamOptions,
+    ) -> impl Stream<Item = Result<Event, Error>> {
+        let camera = self.clone();
+
+        futures_util::stream::once(async move { camera.events().await })
+            .map_ok(move |events| event::event_stream(events, options.clone()).map_err(Error::from))
+            .try_flatten()
+    }
+
+    async fn diagnose_unauthorized(&self) -> Error {
+        match self.clock_offset().await {
+            Ok(Some(offset)) if is_skewed(offset) => Error::ClockSkew { offset },
+            _ => Error::InvalidCredentials,
+        }
+    }
+}
+
+async fn media2_stream_uris(media2: &Client) -> Result<Vec<ProfileStream>, transport::Error> {
+    let profiles = media2::get_profiles(media2).await?;
+    let uris =
+        media2::get_stream_uris_for_profiles(media2, &profiles, StreamProtocol::RtspUnicast).await;
+
+    let streams = profiles.iter().zip(uris).map(|(profile, (token, uri))| {
+        let resolution = profile
+            .configurations
+            .as_ref()
+            .and_then(|configurations| configurations.video_encoder.as_ref())
+            .map(|encoder| &encoder.resolution)
+            .map(|r| (r.width.max(0) as u32, r.height.max(0) as u32));
+
+        let stream = uri.map(|uri| ProfileStream {
+            profile_token: profile.token.0.clone(),
+            profile_name: profile.name.0.clone(),
+            codec: VideoCodec::of_media_profile(profile),
+            resolution,
+            uri,
+        });
+
+        (token, stream)
+    });
+
+    resolved_streams(streams)
+}
+
+/// Streams of the profiles the device returned a URI for, the other profiles are logged and
+/// skipped. Fails with the first error if there are profiles but none has a URI.
+fn resolved_streams(
+    streams: impl IntoIterator<Item = (String, Result<ProfileStream, transport::Error>)>,
+) -> Result<Vec<ProfileStream>, transport::Error> {
+    let mut resolved = vec![];
+    let mut first_error = None;
+
+    for (profile_token, stream) in streams {
+        match stream {
+            Ok(stream) => resolved.push(stream),
+            Err(e) => {
+                debug!("Skipping profile {}: {}", profile_token, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) if resolved.is_empty() => Err(e),
+        _ => Ok(resolved),
+    }
+}
+
+/// Client for the service at `x_addr` with the settings of the device management client.
+fn service_client(devicemgmt: &Client, policy: XAddrPolicy, x_addr: &str) -> Result<Client, Error> {
+    let uri = policy.resolve(devicemgmt.uri(), x_addr)?;
+
+    Ok(devicemgmt.with_uri(&uri))
+}
+
+fn service<'a>(client: &'a Option<Client>, name: &str) -> Result<&'a Client, Error> {
+    client
+        .as_ref()
+        .ok_or_else(|| Error::Unsupported(format!("{} service", name)))
+}
+
+fn is_skewed(offset: chrono::Duration) -> bool {
+    offset.num_milliseconds().unsigned_abs() as u128 > CLOCK_SKEW_THRESHOLD.as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skew() {
+        let date_time = tt::DateTime {
+            time: tt::Time {
+                hour: 23,
+                minute: 59,
+                second: 30,
+            },
+            date: tt::Date {
+                year: 2021,
+                month: 12,
+                day: 31,
+            },
+        };
+
+        assert_eq!(
+            to_chrono(&date_time).unwrap().to_rfc3339(),
+            "2021-12-31T23:59:30+00:00"
+        );
+
+        assert!(!is_skewed(chrono::Duration::seconds(-5)));
+        assert!(is_skewed(chrono::Duration::seconds(-6)));
+        assert!(is_skewed(chrono::Duration::minutes(10)));
+    }
+
+    #[test]
+    fn partial_streams() {
+        let stream = |profile_token: &str| ProfileStream {
+            profile_token: profile_token.to_string(),
+            profile_name: profile_token.to_string(),
+            codec: None,
+            resolution: None,
+            uri: format!("rtsp://192.168.0.2/{}", profile_token),
+        };
+        let error = || transport::Error::Http(reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+
+        assert_eq!(
+            resolved_streams(vec![
+                ("main".to_string(), Ok(stream("main"))),
+                ("broken".to_string(), Err(error())),
+                ("sub".to_string(), Ok(stream("sub"))),
+            ])
+            .unwrap(),
+            vec![stream("main"), stream("sub")]
+        );
+        assert!(matches!(
+            resolved_streams(vec![("broken".to_string(), Err(error()))]),
+            Err(transport::Error::Http(_))
+        ));
+        assert_eq!(resolved_streams(vec![]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn x_addr_policy() {
+        let base = Url::parse("http://203.0.113.7:8080/onvif/device_service").unwrap();
+        let resolve =
+            |policy: XAddrPolicy, x_addr: &str| policy.resolve(&base, x_addr).map(String::from);
+
+        assert_eq!(
+            resolve(XAddrPolicy::AsAdvertised, "http://192.168.0.2:8000/media").unwrap(),
+            "http://192.168.0.2:8000/media"
+        );
+        assert_eq!(
+            resolve(XAddrPolicy::AsAdvertised, "/onvif/media_service").unwrap(),
+            "http://203.0.113.7:8080/onvif/media_service"
+        );
+        assert_eq!(
+            resolve(XAddrPolicy::RewriteHost, "http://192.168.0.2:8000/media").unwrap(),
+            "http://203.0.113.7:8000/media"
+        );
+        assert_eq!(
+            resolve(
+                XAddrPolicy::RequireSameOrigin,
+                "http://203.0.113.7:8080/media"
+            )
+            .unwrap(),
+            "http://203.0.113.7:8080/media"
+        );
+        assert!(matches!(
+            resolve(
+                XAddrPolicy::RequireSameOrigin,
+                "http://203.0.113.7:8000/media"
+            ),
+            Err(Error::ForeignServiceUri { .. })
+        ));
+        assert!(matches!(
+            resolve(XAddrPolicy::AsAdvertised, "http://[::1"),
+            Err(Error::Transport(transport::Error::Deserialization { .. }))
+        ));
+    }
+
+    /// Serves the requests with `respond`, which returns the HTTP status and body of the response
+    /// to a request body, and returns the URI of the device management service.
+    async fn fake_device(respond: fn(&str) -> (&'static str, String)) -> Url {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = Url::parse(&format!(
+            "http://{}/onvif/device_service",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !String::from_utf8_lossy(&request).contains("Envelope>") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                let (status, body) = respond(&String::from_utf8_lossy(&request));
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/soap+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        uri
+    }
+
+    #[tokio::test]
+    async fn clock_skew_with_rejected_requests() {
+        use crate::soap::client::AuthType;
+
+        // Rejects all the requests with credentials, even `GetSystemDateAndTime`, and reports a
+        // time far in the past.
+        let uri = fake_device(|request| {
+            if request.contains("UsernameToken") || !request.contains("GetSystemDateAndTime") {
+                (
+                    "400 Bad Request",
+                    r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:ter="http://www.onvif.org/ver10/error"><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>ter:NotAuthorized</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en">Sender not authorized</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#.to_string(),
+                )
+            } else {
+                (
+                    "200 OK",
+                    r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema"><s:Body><tds:GetSystemDateAndTimeResponse><tds:SystemDateAndTime><tt:DateTimeType>Manual</tt:DateTimeType><tt:DaylightSavings>false</tt:DaylightSavings><tt:UTCDateTime><tt:Time><tt:Hour>12</tt:Hour><tt:Minute>0</tt:Minute><tt:Second>0</tt:Second></tt:Time><tt:Date><tt:Year>2000</tt:Year><tt:Month>1</tt:Month><tt:Day>1</tt:Day></tt:Date></tt:UTCDateTime></tds:SystemDateAndTime></tds:GetSystemDateAndTimeResponse></s:Body></s:Envelope>"#.to_string(),
+                )
+            }
+        })
+        .await;
+
+        let camera = Camera::new(
+            ClientBuilder::new(&uri)
+                .credentials(Some(Credentials {
+                    username: "admin".to_string(),
+                    password: "password".to_string(),
+                }))
+                .auth_type(AuthType::UsernameToken)
+                .auto_sync_clock(false)
+                .build(),
+        );
+
+        match camera.verify_credentials().await {
+            Err(Error::ClockSkew { offset }) => assert!(offset < chrono::Duration::days(-365)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use schema::{
+    devicemgmt, onvif as tt,
+    transport::{self, Transport},
+};
+
+/// Services and features of the device reported by `GetCapabilities`.
+///
+/// Categories the device omits are `None`. Unlike `GetServices`, `GetCapabilities` is
+/// implemented by older devices too, but it only knows about the services of ONVIF 1.x.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub analytics: Option<AnalyticsCapabilities>,
+    pub device: Option<DeviceCapabilities>,
+    pub events: Option<EventCapabilities>,
+    pub imaging: Option<ImagingCapabilities>,
+    pub media: Option<MediaCapabilities>,
+    pub ptz: Option<PtzCapabilities>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalyticsCapabilities {
+    pub x_addr: String,
+    pub rule_support: bool,
+    pub analytics_module_support: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    pub x_addr: String,
+    pub ip_version_6: bool,
+    pub firmware_upgrade: bool,
+    pub system_backup: bool,
+    pub system_logging: bool,
+    pub input_connectors: u32,
+    pub relay_outputs: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventCapabilities {
+    pub x_addr: String,
+    pub ws_subscription_policy_support: bool,
+    pub ws_pull_point_support: bool,
+    pub ws_pausable_subscription_manager_interface_support: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImagingCapabilities {
+    pub x_addr: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaCapabilities {
+    pub x_addr: String,
+    pub rtp_multicast: bool,
+    /// RTP over TCP.
+    pub rtp_tcp: bool,
+    /// RTP interleaved in the RTSP connection.
+    pub rtp_rtsp_tcp: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PtzCapabilities {
+    pub x_addr: String,
+}
+
+/// Fetches the capabilities of all the categories.
+pub async fn get_capabilities<T: Transport>(
+    transport: &T,
+) -> Result<Capabilities, transport::Error> {
+    let capabilities = devicemgmt::get_capabilities(
+        transport,
+        &devicemgmt::GetCapabilities {
+            category: vec![tt::CapabilityCategory::All],
+        },
+    )
+    .await?
+    .capabilities;
+
+    Ok(Capabilities::from(&capabilities))
+}
+
+impl From<&tt::Capabilities> for Capabilities {
+    fn from(capabilities: &tt::Capabilities) -> Self {
+        Self {
+            analytics: capabilities
+                .analytics
+                .first()
+                .map(|analytics| AnalyticsCapabilities {
+                    x_addr: analytics.x_addr.clone(),
+                    rule_support: analytics.rule_support,
+                    analytics_module_support: analytics.analytics_module_support,
+                }),
+            device: capabilities.device.first().map(|device| {
+                let network = device.network.as_ref();
+                let system = device.system.as_ref();
+                let io = device.io.as_ref();
+
+                DeviceCapabilities {
+                    x_addr: device.x_addr.clone(),
+                    ip_version_6: network
+                        .and_then(|network| network.ip_version_6)
+                        .unwrap_or(false),
+                    firmware_upgrade: system.map_or(false, |system| system.firmware_upgrade),
+                    system_backup: system.map_or(false, |system| system.system_backup),
+                    system_logging: system.map_or(false, |system| system.system_logging),
+                    input_connectors: io.and_then(|io| io.input_connectors).unwrap_or(0).max(0)
+                        as u32,
+                    relay_outputs: io.and_then(|io| io.relay_outputs).unwrap_or(0).max(0) as u32,
+                }
+            }),
+            events: capabilities.events.first().map(|events| EventCapabilities {
+                x_addr: events.x_addr.clone(),
+                ws_subscription_policy_support: events.ws_subscription_policy_support,
+                ws_pull_point_support: events.ws_pull_point_support,
+                ws_pausable_subscription_manager_interface_support: events
+                    .ws_pausable_subscription_manager_interface_support,
+            }),
+            imaging: capabilities
+                .imaging
+                .first()
+                .map(|imaging| ImagingCapabilities {
+                    x_addr: imaging.x_addr.clone(),
+                }),
+            media: capabilities.media.first().map(|media| {
+                let streaming = &media.streaming_capabilities;
+
+                MediaCapabilities {
+                    x_addr: media.x_addr.clone(),
+                    rtp_multicast: streaming.rtp_multicast.unwrap_or(false),
+                    rtp_tcp: streaming.rtp_tcp.unwrap_or(false),
+                    rtp_rtsp_tcp: streaming.rtp_rtsp_tcp.unwrap_or(false),
+                }
+            }),
+            ptz: capabilities.ptz.first().map(|ptz| PtzCapabilities {
+                x_addr: ptz.x_addr.clone(),
+            }),
+        }
+    }
+}
+
+#[tokio::test]
+async fn partial_capabilities() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:GetCapabilitiesResponse
+                xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+                xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tds:Capabilities>
+                <tt:Events>
+                    <tt:XAddr>http://192.168.0.2/onvif/event_service</tt:XAddr>
+                    <tt:WSSubscriptionPolicySupport>false</tt:WSSubscriptionPolicySupport>
+                    <tt:WSPullPointSupport>true</tt:WSPullPointSupport>
+                    <tt:WSPausableSubscriptionManagerInterfaceSupport>false</tt:WSPausableSubscriptionManagerInterfaceSupport>
+                </tt:Events>
+                <tt:Media>
+                    <tt:XAddr>http://192.168.0.2/onvif/media_service</tt:XAddr>
+                    <tt:StreamingCapabilities>
+                        <tt:RTPMulticast>false</tt:RTPMulticast>
+                        <tt:RTP_TCP>true</tt:RTP_TCP>
+                        <tt:RTP_RTSP_TCP>true</tt:RTP_RTSP_TCP>
+                    </tt:StreamingCapabilities>
+                </tt:Media>
+            </tds:Capabilities>
+        </tds:GetCapabilitiesResponse>
+        "#,
+    );
+
+    let capabilities = get_capabilities(&transport).await.unwrap();
+
+    assert_eq!(
+        capabilities,
+        Capabilities {
+            events: Some(EventCapabilities {
+                x_addr: "http://192.168.0.2/onvif/event_service".to_string(),
+                ws_subscription_policy_support: false,
+                ws_pull_point_support: true,
+                ws_pausable_subscription_manager_interface_support: false,
+            }),
+            media: Some(MediaCapabilities {
+                x_addr: "http://192.168.0.2/onvif/media_service".to_string(),
+                rtp_multicast: false,
+                rtp_tcp: true,
+                rtp_rtsp_tcp: true,
+            }),
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(
+        transport
+            .last_request()
+            .get_child("Category")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "All"
+    );
+}
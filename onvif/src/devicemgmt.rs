@@ -0,0 +1,905 @@
+use crate::utils::fault::is_connection_dropped;
+use schema::{
+    devicemgmt,
+    onvif::{
+        self as tt, Dnsname, Ipaddress, Iptype, Ipv4Address, Ipv6Address, NetworkHost,
+        NetworkHostType, ReferenceToken,
+    },
+    transport::{self, Transport},
+};
+use std::{
+    convert::TryFrom,
+    net::{IpAddr, Ipv4Addr},
+};
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request was not sent because an argument is invalid, e.g. no server is given while
+    /// DHCP is off.
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// The device refused the operation with `ter:NotAuthorized`, e.g. because the caller tried
+    /// to create a user of a higher level than its own, or rejected the credentials.
+    #[error("Not authorized: {0}")]
+    NotAuthorized(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] transport::Error),
+}
+
+/// Identification of the device, as reported by `GetDeviceInformation`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInformation {
+    pub manufacturer: String,
+    pub model: String,
+    pub firmware_version: String,
+    pub serial_number: String,
+    pub hardware_id: String,
+}
+
+/// Fetches the manufacturer, model, firmware version and serial number of the device.
+pub async fn get_device_information<T: Transport>(
+    transport: &T,
+) -> Result<DeviceInformation, transport::Error> {
+    let response = devicemgmt::get_device_information(transport, &Default::default()).await?;
+
+    Ok(DeviceInformation {
+        manufacturer: response.manufacturer,
+        model: response.model,
+        firmware_version: response.firmware_version,
+        serial_number: response.serial_number,
+        hardware_id: response.hardware_id,
+    })
+}
+
+/// Reboots the device and returns the message it responds with, e.g. the expected downtime.
+///
+/// Devices often close the connection right after receiving the request, or while sending the
+/// response. As the reboot has started by then, it's not an error: `None` is returned instead of
+/// the message.
+pub async fn system_reboot<T: Transport>(
+    transport: &T,
+) -> Result<Option<String>, transport::Error> {
+    match devicemgmt::system_reboot(transport, &Default::default()).await {
+        Ok(response) => Ok(Some(response.message)),
+        Err(e) if is_connection_dropped(&e) => {
+            debug!("Connection dropped after the reboot request: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// NTP settings of the device, as reported by `GetNTP`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NtpSettings {
+    pub from_dhcp: bool,
+    /// Servers obtained from DHCP, as IP addresses or host names.
+    pub dhcp_servers: Vec<String>,
+    /// Servers set manually, as IP addresses or host names.
+    pub manual_servers: Vec<String>,
+}
+
+/// Fetches the NTP servers of the device.
+pub async fn get_ntp<T: Transport>(transport: &T) -> Result<NtpSettings, transport::Error> {
+    let info = devicemgmt::get_ntp(transport, &Default::default())
+        .await?
+        .ntp_information;
+
+    Ok(NtpSettings {
+        from_dhcp: info.from_dhcp,
+        dhcp_servers: info.ntp_from_dhcp.iter().filter_map(host_name).collect(),
+        manual_servers: info.ntp_manual.iter().filter_map(host_name).collect(),
+    })
+}
+
+/// Makes the device synchronize its clock with `servers` (IP addresses or host names), or with
+/// the servers obtained from DHCP if `from_dhcp` is set.
+///
+/// With a working NTP server, the device clock stays close enough to the local one for the
+/// UsernameToken authentication to work. Returns [`Error::InvalidArgument`] without sending the
+/// request if `from_dhcp` isn't set and no server is given.
+pub async fn set_ntp<T: Transport>(
+    transport: &T,
+    from_dhcp: bool,
+    servers: &[&str],
+) -> Result<(), Error> {
+    if !from_dhcp && servers.is_empty() {
+        return Err(Error::InvalidArgument(
+            "At least one NTP server is required when FromDHCP is false".to_string(),
+        ));
+    }
+    if let Some(server) = servers.iter().find(|server| server.trim().is_empty()) {
+        return Err(Error::InvalidArgument(format!(
+            "Invalid NTP server: {:?}",
+            server
+        )));
+    }
+
+    devicemgmt::set_ntp(
+        transport,
+        &devicemgmt::SetNTP {
+            from_dhcp,
+            ntp_manual: servers.iter().map(|server| network_host(server)).collect(),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// DNS settings of the device, as reported by `GetDNS`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsSettings {
+    pub from_dhcp: bool,
+    pub search_domains: Vec<String>,
+    /// Servers obtained from DHCP.
+    pub dhcp_servers: Vec<IpAddr>,
+    /// Servers set manually.
+    pub manual_servers: Vec<IpAddr>,
+}
+
+/// Fetches the DNS servers and search domains of the device.
+///
+/// Addresses which can't be parsed are skipped.
+pub async fn get_dns<T: Transport>(transport: &T) -> Result<DnsSettings, transport::Error> {
+    let info = devicemgmt::get_dns(transport, &Default::default())
+        .await?
+        .dns_information;
+
+    Ok(DnsSettings {
+        from_dhcp: info.from_dhcp,
+        search_domains: info.search_domain,
+        dhcp_servers: info.dns_from_dhcp.iter().filter_map(ip_address).collect(),
+        manual_servers: info.dns_manual.iter().filter_map(ip_address).collect(),
+    })
+}
+
+/// Makes the device resolve names with `servers` and `search_domains`, or with the settings
+/// obtained from DHCP if `from_dhcp` is set.
+///
+/// Returns [`Error::InvalidArgument`] without sending the request if `from_dhcp` isn't set and
+/// no server is given.
+pub async fn set_dns<T: Transport>(
+    transport: &T,
+    from_dhcp: bool,
+    servers: &[IpAddr],
+    search_domains: &[&str],
+) -> Result<(), Error> {
+    if !from_dhcp && servers.is_empty() {
+        return Err(Error::InvalidArgument(
+            "At least one DNS server is required when FromDHCP is false".to_string(),
+        ));
+    }
+
+    devicemgmt::set_dns(
+        transport,
+        &devicemgmt::SetDNS {
+            from_dhcp,
+            search_domain: search_domains.iter().map(|s| s.to_string()).collect(),
+            dns_manual: servers.iter().map(|server| ipaddress(*server)).collect(),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Access level of a user account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserLevel {
+    Administrator,
+    Operator,
+    User,
+    Anonymous,
+    Extended,
+    /// Level the crate doesn't know.
+    Other(String),
+}
+
+impl From<&tt::UserLevel> for UserLevel {
+    fn from(level: &tt::UserLevel) -> Self {
+        match level {
+            tt::UserLevel::Administrator => Self::Administrator,
+            tt::UserLevel::Operator => Self::Operator,
+            tt::UserLevel::User => Self::User,
+            tt::UserLevel::Anonymous => Self::Anonymous,
+            tt::UserLevel::Extended => Self::Extended,
+            tt::UserLevel::__Unknown__(level) => Self::Other(level.clone()),
+        }
+    }
+}
+
+impl From<&UserLevel> for tt::UserLevel {
+    fn from(level: &UserLevel) -> Self {
+        match level {
+            UserLevel::Administrator => Self::Administrator,
+            UserLevel::Operator => Self::Operator,
+            UserLevel::User => Self::User,
+            UserLevel::Anonymous => Self::Anonymous,
+            UserLevel::Extended => Self::Extended,
+            UserLevel::Other(level) => Self::__Unknown__(level.clone()),
+        }
+    }
+}
+
+/// User account of the device, as reported by `GetUsers` (passwords are not reported).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAccount {
+    pub username: String,
+    pub level: UserLevel,
+}
+
+/// Lists the user accounts of the device.
+pub async fn get_users<T: Transport>(transport: &T) -> Result<Vec<UserAccount>, Error> {
+    let response = devicemgmt::get_users(transport, &Default::default())
+        .await
+        .map_err(user_error)?;
+
+    Ok(response
+        .user
+        .iter()
+        .map(|user| UserAccount {
+            username: user.username.clone(),
+            level: UserLevel::from(&user.user_level),
+        })
+        .collect())
+}
+
+/// Creates a user account.
+///
+/// Devices don't let a caller create a user of a higher level than its own, which is reported as
+/// [`Error::NotAuthorized`].
+pub async fn create_user<T: Transport>(
+    transport: &T,
+    username: &str,
+    password: &str,
+    level: &UserLevel,
+) -> Result<(), Error> {
+    devicemgmt::create_users(
+        transport,
+        &devicemgmt::CreateUsers {
+            user: vec![user(username, password, level)?],
+        },
+    )
+    .await
+    .map_err(user_error)?;
+
+    Ok(())
+}
+
+/// Changes the password and the level of an existing user account.
+pub async fn set_user<T: Transport>(
+    transport: &T,
+    username: &str,
+    password: &str,
+    level: &UserLevel,
+) -> Result<(), Error> {
+    devicemgmt::set_user(
+        transport,
+        &devicemgmt::SetUser {
+            user: vec![user(username, password, level)?],
+        },
+    )
+    .await
+    .map_err(user_error)?;
+
+    Ok(())
+}
+
+/// Deletes user accounts. Either all of them are deleted or none is.
+pub async fn delete_users<T: Transport>(transport: &T, usernames: &[&str]) -> Result<(), Error> {
+    devicemgmt::delete_users(
+        transport,
+        &devicemgmt::DeleteUsers {
+            username: usernames
+                .iter()
+                .map(|username| username.to_string())
+                .collect(),
+        },
+    )
+    .await
+    .map_err(user_error)?;
+
+    Ok(())
+}
+
+fn user(username: &str, password: &str, level: &UserLevel) -> Result<tt::User, Error> {
+    if username.is_empty() {
+        return Err(Error::InvalidArgument("Empty username".to_string()));
+    }
+
+    Ok(tt::User {
+        username: username.to_string(),
+        password: Some(password.to_string()),
+        user_level: level.into(),
+        extension: None,
+    })
+}
+
+/// Surfaces the `ter:NotAuthorized` faults, which the client reports as
+/// [`transport::Error::Unauthorized`].
+fn user_error(e: transport::Error) -> Error {
+    match e {
+        transport::Error::Unauthorized(message) => Error::NotAuthorized(message),
+        e => e.into(),
+    }
+}
+
+/// IPv4 address with the length of its network prefix, e.g. 24 for a 255.255.255.0 netmask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Prefix {
+    pub address: Ipv4Addr,
+    pub prefix_length: u8,
+}
+
+/// IPv4 configuration of a network interface.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ipv4Settings {
+    pub enabled: bool,
+    pub dhcp: bool,
+    /// Addresses set manually.
+    pub manual: Vec<Ipv4Prefix>,
+    pub from_dhcp: Option<Ipv4Prefix>,
+    pub link_local: Option<Ipv4Prefix>,
+}
+
+/// Network interface of the device, as reported by `GetNetworkInterfaces`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkInterface {
+    /// Token to pass to [`set_network_interface`].
+    pub token: String,
+    /// Name of the interface, e.g. eth0.
+    pub name: Option<String>,
+    pub hw_address: Option<String>,
+    pub enabled: bool,
+    /// `None` if the interface has no IPv4 configuration.
+    pub ipv4: Option<Ipv4Settings>,
+}
+
+/// Result of [`set_network_interface`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkInterfaceChange {
+    /// Address the interface was configured with, `None` if it now uses DHCP.
+    pub address: Option<Ipv4Prefix>,
+    /// Whether the device has to be rebooted to apply the change, `None` if the connection was
+    /// dropped before the response was received.
+    pub reboot_needed: Option<bool>,
+}
+
+/// Fetches the network interfaces of the device.
+///
+/// Addresses which can't be parsed are skipped.
+pub async fn get_network_interfaces<T: Transport>(
+    transport: &T,
+) -> Result<Vec<NetworkInterface>, transport::Error> {
+    let response = devicemgmt::get_network_interfaces(transport, &Default::default()).await?;
+
+    Ok(response
+        .network_interfaces
+        .iter()
+        .map(|interface| NetworkInterface {
+            token: interface.token.0.clone(),
+            name: interface.info.as_ref().and_then(|info| info.name.clone()),
+            hw_address: interface
+                .info
+                .as_ref()
+                .map(|info| info.hw_address.0.clone()),
+            enabled: interface.enabled,
+            ipv4: interface.i_pv_4.first().map(|ipv4| Ipv4Settings {
+                enabled: ipv4.enabled,
+                dhcp: ipv4.config.dhcp,
+                manual: ipv4.config.manual.iter().filter_map(ipv4_prefix).collect(),
+                from_dhcp: ipv4.config.from_dhcp.as_ref().and_then(ipv4_prefix),
+                link_local: ipv4.config.link_local.as_ref().and_then(ipv4_prefix),
+            }),
+        })
+        .collect())
+}
+
+/// Switches the interface `token` to DHCP if `dhcp` is set, or to the static `address`
+/// otherwise, and returns the address it was configured with.
+///
+/// Changing the address usually makes the device drop the connection, possibly before the
+/// response is received. As the device has got the request by then, it's not an error:
+/// [`NetworkInterfaceChange::reboot_needed`] is `None` instead. The device can then be reached at
+/// the new address only. Returns [`Error::InvalidArgument`] without sending the request if
+/// `dhcp` isn't set and no valid address is given.
+pub async fn set_network_interface<T: Transport>(
+    transport: &T,
+    token: &str,
+    dhcp: bool,
+    address: Option<Ipv4Prefix>,
+) -> Result<NetworkInterfaceChange, Error> {
+    let manual = match address {
+        _ if dhcp => None,
+        Some(address) if address.prefix_length > 32 => {
+            return Err(Error::InvalidArgument(format!(
+                "Invalid prefix length: {}",
+                address.prefix_length
+            )))
+        }
+        Some(address) => Some(address),
+        None => {
+            return Err(Error::InvalidArgument(
+                "An address is required when DHCP is off".to_string(),
+            ))
+        }
+    };
+
+    let request = devicemgmt::SetNetworkInterfaces {
+        interface_token: ReferenceToken(token.to_string()),
+        network_interface: tt::NetworkInterfaceSetConfiguration {
+            i_pv_4: vec![tt::Ipv4NetworkInterfaceSetConfiguration {
+                enabled: Some(true),
+                manual: manual
+                    .iter()
+                    .map(|address| tt::PrefixedIPv4Address {
+                        address: Ipv4Address(address.address.to_string()),
+                        prefix_length: address.prefix_length.into(),
+                    })
+                    .collect(),
+                dhcp: Some(dhcp),
+            }],
+            ..Default::default()
+        },
+    };
+
+    let reboot_needed = match devicemgmt::set_network_interfaces(transport, &request).await {
+        Ok(response) => Some(response.reboot_needed),
+        Err(e) if is_connection_dropped(&e) => {
+            debug!(
+                "Connection dropped after the network interface change: {}",
+                e
+            );
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(NetworkInterfaceChange {
+        address: manual,
+        reboot_needed,
+    })
+}
+
+/// Fetches the default gateways of the device.
+///
+/// Addresses which can't be parsed are skipped.
+pub async fn get_network_default_gateway<T: Transport>(
+    transport: &T,
+) -> Result<Vec<IpAddr>, transport::Error> {
+    let gateway = devicemgmt::get_network_default_gateway(transport, &Default::default())
+        .await?
+        .network_gateway;
+
+    Ok(gateway
+        .i_pv_4_address
+        .iter()
+        .map(|address| &address.0)
+        .chain(gateway.i_pv_6_address.iter().map(|address| &address.0))
+        .filter_map(|text| {
+            text.parse()
+                .map_err(|_| debug!("Skipping invalid IP address {:?}", text))
+                .ok()
+        })
+        .collect())
+}
+
+/// Makes the device use `gateways` as its default IPv4 and IPv6 gateways.
+pub async fn set_network_default_gateway<T: Transport>(
+    transport: &T,
+    gateways: &[IpAddr],
+) -> Result<(), Error> {
+    let (v4, v6): (Vec<_>, Vec<_>) = gateways.iter().partition(|gateway| gateway.is_ipv4());
+
+    devicemgmt::set_network_default_gateway(
+        transport,
+        &devicemgmt::SetNetworkDefaultGateway {
+            i_pv_4_address: v4
+                .iter()
+                .map(|gateway| Ipv4Address(gateway.to_string()))
+                .collect(),
+            i_pv_6_address: v6
+                .iter()
+                .map(|gateway| Ipv6Address(gateway.to_string()))
+                .collect(),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// `NetworkHost` of an IP address or a host name.
+fn network_host(host: &str) -> NetworkHost {
+    match host.parse() {
+        Ok(IpAddr::V4(_)) => NetworkHost {
+            _type: NetworkHostType::Ipv4,
+            i_pv_4_address: Some(Ipv4Address(host.to_string())),
+            ..Default::default()
+        },
+        Ok(IpAddr::V6(_)) => NetworkHost {
+            _type: NetworkHostType::Ipv6,
+            i_pv_6_address: Some(Ipv6Address(host.to_string())),
+            ..Default::default()
+        },
+        Err(_) => NetworkHost {
+            _type: NetworkHostType::Dns,
+            dn_sname: Some(Dnsname(host.to_string())),
+            ..Default::default()
+        },
+    }
+}
+
+/// IP address or host name of `host`, whichever its type says it has.
+fn host_name(host: &NetworkHost) -> Option<String> {
+    match host._type {
+        NetworkHostType::Ipv4 => host.i_pv_4_address.as_ref().map(|a| a.0.clone()),
+        NetworkHostType::Ipv6 => host.i_pv_6_address.as_ref().map(|a| a.0.clone()),
+        _ => host.dn_sname.as_ref().map(|name| name.0.clone()),
+    }
+}
+
+fn ipaddress(address: IpAddr) -> Ipaddress {
+    match address {
+        IpAddr::V4(v4) => Ipaddress {
+            _type: Iptype::Ipv4,
+            i_pv_4_address: Some(Ipv4Address(v4.to_string())),
+            i_pv_6_address: None,
+        },
+        IpAddr::V6(v6) => Ipaddress {
+            _type: Iptype::Ipv6,
+            i_pv_4_address: None,
+            i_pv_6_address: Some(Ipv6Address(v6.to_string())),
+        },
+    }
+}
+
+fn ipv4_prefix(address: &tt::PrefixedIPv4Address) -> Option<Ipv4Prefix> {
+    let parsed = address.address.0.parse().ok();
+    let prefix_length = u8::try_from(address.prefix_length)
+        .ok()
+        .filter(|length| *length <= 32);
+
+    match (parsed, prefix_length) {
+        (Some(address), Some(prefix_length)) => Some(Ipv4Prefix {
+            address,
+            prefix_length,
+        }),
+        _ => {
+            debug!(
+                "Skipping invalid IPv4 address {:?}/{}",
+                address.address.0, address.prefix_length
+            );
+            None
+        }
+    }
+}
+
+fn ip_address(address: &Ipaddress) -> Option<IpAddr> {
+    let text = match address._type {
+        Iptype::Ipv6 => &address.i_pv_6_address.as_ref()?.0,
+        _ => &address.i_pv_4_address.as_ref()?.0,
+    };
+
+    text.parse()
+        .map_err(|_| debug!("Skipping invalid IP address {:?}", text))
+        .ok()
+}
+
+#[tokio::test]
+async fn device_information() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:GetDeviceInformationResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+            <tds:Manufacturer>Acme</tds:Manufacturer>
+            <tds:Model>C-1</tds:Model>
+            <tds:FirmwareVersion>1.2.3</tds:FirmwareVersion>
+            <tds:SerialNumber>0123456789</tds:SerialNumber>
+            <tds:HardwareId>1.0</tds:HardwareId>
+        </tds:GetDeviceInformationResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_device_information(&transport).await.unwrap(),
+        DeviceInformation {
+            manufacturer: "Acme".to_string(),
+            model: "C-1".to_string(),
+            firmware_version: "1.2.3".to_string(),
+            serial_number: "0123456789".to_string(),
+            hardware_id: "1.0".to_string(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn reboot() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:SystemRebootResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+            <tds:Message>Rebooting in 30 seconds</tds:Message>
+        </tds:SystemRebootResponse>
+        "#,
+    );
+    assert_eq!(
+        system_reboot(&transport).await.unwrap().as_deref(),
+        Some("Rebooting in 30 seconds")
+    );
+    assert_eq!(transport.last_request().name, "SystemReboot");
+
+    let dropped = RecordingTransport::error(
+        transport::Error::ConnectionDropped,
+        "error sending request: connection closed before message completed",
+    );
+    assert_eq!(system_reboot(&dropped).await.unwrap(), None);
+
+    let refused = RecordingTransport::error(
+        transport::Error::Unauthorized,
+        "SOAP fault env:Sender/ter:NotAuthorized",
+    );
+    assert!(system_reboot(&refused).await.is_err());
+}
+
+#[tokio::test]
+async fn reboot_with_retries() {
+    use crate::soap::client::ClientBuilder;
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = url::Url::parse(&format!(
+        "http://{}/onvif/device_service",
+        listener.local_addr().unwrap()
+    ))
+    .unwrap();
+
+    // The device drops the connection as it reboots, and refuses the next ones.
+    let device = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = stream.read(&mut [0; 4096]).await.unwrap();
+    });
+
+    let client = ClientBuilder::new(&uri)
+        .retries(1)
+        .retry_backoff(std::time::Duration::from_millis(1))
+        .build();
+    let result = system_reboot(&client).await;
+    device.await.unwrap();
+
+    assert_eq!(result.unwrap(), None);
+    assert_eq!(client.stats().retries, 0);
+}
+
+#[tokio::test]
+async fn ntp_and_dns() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<tds:SetNTPResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl"/>"#,
+    );
+
+    assert!(matches!(
+        set_ntp(&transport, false, &[]).await,
+        Err(Error::InvalidArgument(_))
+    ));
+
+    set_ntp(&transport, false, &["192.168.0.1", "ntp.example.com"])
+        .await
+        .unwrap();
+    let request = transport.last_request();
+    let hosts: Vec<_> = request
+        .children
+        .iter()
+        .filter_map(|node| node.as_element())
+        .filter(|element| element.name == "NTPManual")
+        .map(|host| {
+            host.children
+                .iter()
+                .filter_map(|node| node.as_element())
+                .filter_map(|element| element.get_text())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    assert_eq!(hosts, ["IPv4 192.168.0.1", "DNS ntp.example.com"]);
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:GetDNSResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+                            xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tds:DNSInformation>
+                <tt:FromDHCP>false</tt:FromDHCP>
+                <tt:SearchDomain>example.com</tt:SearchDomain>
+                <tt:DNSManual>
+                    <tt:Type>IPv4</tt:Type>
+                    <tt:IPv4Address>10.0.0.53</tt:IPv4Address>
+                </tt:DNSManual>
+                <tt:DNSManual>
+                    <tt:Type>IPv6</tt:Type>
+                    <tt:IPv6Address>fd00::53</tt:IPv6Address>
+                </tt:DNSManual>
+            </tds:DNSInformation>
+        </tds:GetDNSResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_dns(&transport).await.unwrap(),
+        DnsSettings {
+            from_dhcp: false,
+            search_domains: vec!["example.com".to_string()],
+            dhcp_servers: vec![],
+            manual_servers: vec!["10.0.0.53".parse().unwrap(), "fd00::53".parse().unwrap()],
+        }
+    );
+
+    assert!(matches!(
+        set_dns(&transport, false, &[], &[]).await,
+        Err(Error::InvalidArgument(_))
+    ));
+}
+
+#[tokio::test]
+async fn users() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:GetUsersResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+                              xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tds:User>
+                <tt:Username>admin</tt:Username>
+                <tt:UserLevel>Administrator</tt:UserLevel>
+            </tds:User>
+            <tds:User>
+                <tt:Username>viewer</tt:Username>
+                <tt:UserLevel>User</tt:UserLevel>
+            </tds:User>
+        </tds:GetUsersResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_users(&transport).await.unwrap(),
+        [
+            UserAccount {
+                username: "admin".to_string(),
+                level: UserLevel::Administrator,
+            },
+            UserAccount {
+                username: "viewer".to_string(),
+                level: UserLevel::User,
+            },
+        ]
+    );
+
+    let transport = RecordingTransport::new(
+        r#"<tds:CreateUsersResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl"/>"#,
+    );
+    create_user(&transport, "service", "secret", &UserLevel::Operator)
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    let user = request.get_child("User").unwrap();
+    let text = |name: &str| user.get_child(name).unwrap().get_text().unwrap();
+    assert_eq!(text("Username"), "service");
+    assert_eq!(text("Password"), "secret");
+    assert_eq!(text("UserLevel"), "Operator");
+
+    let denied = RecordingTransport::error(transport::Error::Unauthorized, "ter:NotAuthorized");
+    assert!(matches!(
+        create_user(&denied, "root", "secret", &UserLevel::Administrator).await,
+        Err(Error::NotAuthorized(_))
+    ));
+}
+
+#[tokio::test]
+async fn network_interfaces() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tds:GetNetworkInterfacesResponse xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+                                          xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tds:NetworkInterfaces token="eth0">
+                <tt:Enabled>true</tt:Enabled>
+                <tt:Info>
+                    <tt:Name>eth0</tt:Name>
+                    <tt:HwAddress>00:11:22:33:44:55</tt:HwAddress>
+                </tt:Info>
+                <tt:IPv4>
+                    <tt:Enabled>true</tt:Enabled>
+                    <tt:Config>
+                        <tt:FromDHCP>
+                            <tt:Address>192.168.0.10</tt:Address>
+                            <tt:PrefixLength>24</tt:PrefixLength>
+                        </tt:FromDHCP>
+                        <tt:DHCP>true</tt:DHCP>
+                    </tt:Config>
+                </tt:IPv4>
+            </tds:NetworkInterfaces>
+        </tds:GetNetworkInterfacesResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_network_interfaces(&transport).await.unwrap(),
+        [NetworkInterface {
+            token: "eth0".to_string(),
+            name: Some("eth0".to_string()),
+            hw_address: Some("00:11:22:33:44:55".to_string()),
+            enabled: true,
+            ipv4: Some(Ipv4Settings {
+                enabled: true,
+                dhcp: true,
+                manual: vec![],
+                from_dhcp: Some(Ipv4Prefix {
+                    address: Ipv4Addr::new(192, 168, 0, 10),
+                    prefix_length: 24,
+                }),
+                link_local: None,
+            }),
+        }]
+    );
+
+    let address = Ipv4Prefix {
+        address: Ipv4Addr::new(10, 0, 0, 2),
+        prefix_length: 8,
+    };
+
+    let transport = RecordingTransport::error(
+        transport::Error::ConnectionDropped,
+        "connection closed before message completed",
+    );
+    assert_eq!(
+        set_network_interface(&transport, "eth0", false, Some(address))
+            .await
+            .unwrap(),
+        NetworkInterfaceChange {
+            address: Some(address),
+            reboot_needed: None,
+        }
+    );
+
+    let request = transport.last_request();
+    assert_eq!(
+        request
+            .get_child("InterfaceToken")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "eth0"
+    );
+    let ipv4 = request
+        .get_child("NetworkInterface")
+        .and_then(|interface| interface.get_child("IPv4"))
+        .unwrap();
+    assert_eq!(ipv4.get_child("DHCP").unwrap().get_text().unwrap(), "false");
+    let manual = ipv4.get_child("Manual").unwrap();
+    assert_eq!(
+        manual.get_child("Address").unwrap().get_text().unwrap(),
+        "10.0.0.2"
+    );
+    assert_eq!(
+        manual
+            .get_child("PrefixLength")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "8"
+    );
+
+    assert!(matches!(
+        set_network_interface(&transport, "eth0", false, None).await,
+        Err(Error::InvalidArgument(_))
+    ));
+}
@@ -1,24 +1,42 @@
 use futures_core::stream::Stream;
-use schema::ws_discovery::{probe, probe_matches};
+use schema::ws_discovery::{announcement, probe, probe_matches};
 use std::{
-    collections::HashSet,
+    collections::{hash_map::Entry, HashMap, HashSet},
     fmt::{Debug, Formatter},
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6},
+    pin::Pin,
     sync::Arc,
+    task::{Context, Poll},
 };
 use thiserror::Error;
 use tokio::{
     io,
     net::UdpSocket,
-    sync::mpsc::channel,
+    sync::mpsc::{channel, Receiver},
+    task::JoinHandle,
     time::{timeout, Duration},
 };
-use tokio_stream::wrappers::ReceiverStream;
 use tracing::debug;
 use url::Url;
 
 use crate::utils::{display_list::DisplayList, hash::calculate_hash};
 
+/// How long [`DiscoveryBuilder`] waits for responses, unless [`DiscoveryBuilder::duration`] is
+/// called.
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+
+const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+/// Link-local scope, i.e. `FF02::C`.
+const MULTI_IPV6_ADDR: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xc);
+const MULTI_PORT: u16 = 3702;
+
+/// Largest number of hosts [`DiscoveryBuilder::unicast_scan`] agrees to probe (a `/20` network).
+pub const MAX_UNICAST_SCAN_HOSTS: u32 = 4096;
+
+/// Number of unicast probes sent at once by [`DiscoveryBuilder::unicast_scan`].
+const UNICAST_SCAN_BATCH: usize = 64;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Network error: {0}")]
@@ -29,40 +47,126 @@ pub enum Error {
 
     #[error("Unsupported feature: {0}")]
     Unsupported(String),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("No response within the discovery duration")]
+    Timeout,
 }
 
 #[derive(Clone, Eq, Hash, PartialEq)]
 pub struct Device {
+    /// Address of the `EndpointReference`, usually a `urn:uuid:` URN which identifies the device
+    /// across network interfaces and restarts.
+    pub endpoint_reference: Option<String>,
     pub name: Option<String>,
     pub urls: Vec<Url>,
+    /// Hardware model from the `onvif://www.onvif.org/hardware/` scope.
+    pub hardware: Option<String>,
+    /// Location from the `onvif://www.onvif.org/location/` scope.
+    pub location: Option<String>,
+    /// ONVIF profiles from the `onvif://www.onvif.org/Profile/` scopes, like `Streaming` or `S`.
+    pub profiles: Vec<String>,
+    /// All scopes of the device, as advertised (i.e. percent-encoded).
+    pub scopes: Vec<String>,
+    /// Local address the response was received on, unless the socket was bound to the
+    /// unspecified address. Useful with
+    /// [`listen_all_interfaces`](DiscoveryBuilder::listen_all_interfaces) to pick a route.
+    pub interface: Option<IpAddr>,
 }
 
 impl Debug for Device {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Device")
+            .field("endpoint_reference", &self.endpoint_reference)
             .field("name", &self.name)
             .field("url", &DisplayList(&self.urls))
+            .field("hardware", &self.hardware)
+            .field("location", &self.location)
+            .field("profiles", &self.profiles)
+            .field("scopes", &self.scopes)
+            .field("interface", &self.interface)
             .finish()
     }
 }
 
+impl Device {
+    /// Picks the service URL to connect to from `local_addr`, e.g. the
+    /// [`interface`](Device::interface) the device was heard on.
+    ///
+    /// URLs with an IP host are kept if the host is in the network of `local_addr`, whose prefix
+    /// is looked up among the local interfaces (if it's not one of them, any host of the same IP
+    /// version is kept). URLs with a host name are kept too, after the ones in the network, as
+    /// they may resolve to any address. HTTPS URLs come first if `prefer_https`, and HTTP ones
+    /// otherwise, falling back to the other scheme. Returns `None` if no URL is left.
+    pub fn best_service_url(&self, prefer_https: bool, local_addr: IpAddr) -> Option<Url> {
+        best_service_url(
+            &self.urls,
+            prefer_https,
+            local_addr,
+            local_netmask(local_addr),
+        )
+        .cloned()
+    }
+}
+
+/// `Hello` or `Bye` message multicast by a device, see [`DiscoveryBuilder::listen`].
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Announcement {
+    pub kind: AnnouncementKind,
+    /// Device which sent the message. `Bye` messages usually carry no scopes and addresses.
+    pub device: Device,
+    /// `InstanceId` of the message's `AppSequence`. Devices increment it each time they restart,
+    /// so a changed value means the device has rebooted, even if no `Bye` was seen.
+    pub instance_id: Option<u64>,
+    /// `MessageNumber` of the message's `AppSequence`.
+    pub message_number: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum AnnouncementKind {
+    /// The device has joined the network.
+    Hello,
+    /// The device is leaving the network.
+    Bye,
+}
+
 #[derive(Debug, Clone)]
 pub struct DiscoveryBuilder {
     duration: Duration,
     listen_address: IpAddr,
+    all_interfaces: bool,
+    scope_id: u32,
+    multicast_addr: Option<SocketAddr>,
+    max_devices: Option<usize>,
+    probe_retries: u32,
+    probe_interval: Duration,
+    device_type: Option<String>,
+    scope_filter: Option<String>,
 }
 
 impl Default for DiscoveryBuilder {
     fn default() -> Self {
         Self {
-            duration: Duration::from_secs(5),
+            duration: DEFAULT_DURATION,
             listen_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            all_interfaces: false,
+            scope_id: 0,
+            multicast_addr: None,
+            max_devices: None,
+            probe_retries: 2,
+            probe_interval: Duration::from_millis(500),
+            device_type: None,
+            scope_filter: None,
         }
     }
 }
 
 impl DiscoveryBuilder {
-    /// How long to listen for the responses from the network.
+    /// How long to listen for the responses from the network. Defaults to [`DEFAULT_DURATION`].
+    ///
+    /// Once it elapses, the socket is closed and the stream of devices ends.
     pub fn duration(&mut self, duration: Duration) -> &mut Self {
         self.duration = duration;
         self
@@ -73,16 +177,104 @@ impl DiscoveryBuilder {
     /// By default, it is 0.0.0.0 which is fine for a single-NIC case. With multiple NICs, it's
     /// problematic because 0.0.0.0 is routed to only one NIC, but you may want to run the discovery
     /// on a specific network.
+    ///
+    /// With an IPv6 address (like `::` or `fe80::1`), the discovery runs over IPv6 on the
+    /// `FF02::C` multicast group, see [`scope_id`](Self::scope_id).
     pub fn listen_address(&mut self, listen_address: IpAddr) -> &mut Self {
         self.listen_address = listen_address;
         self
     }
 
+    /// Makes [`run`](Self::run) probe on each IPv4 address of the non-loopback network interfaces
+    /// of the machine, instead of only on the [`listen_address`](Self::listen_address).
+    ///
+    /// The responses from all interfaces are merged into one stream, and [`Device::interface`]
+    /// tells which interface a device was heard on. Interfaces which fail to send the probe are
+    /// skipped.
+    pub fn listen_all_interfaces(&mut self) -> &mut Self {
+        self.all_interfaces = true;
+        self
+    }
+
+    /// Index of the network interface for IPv6 discovery, i.e. the `2` of `fe80::1%2`.
+    ///
+    /// It's required with link-local listen addresses, and it picks the interface the multicast
+    /// group is joined on. The default, 0, lets the OS pick the interface.
+    pub fn scope_id(&mut self, scope_id: u32) -> &mut Self {
+        self.scope_id = scope_id;
+        self
+    }
+
+    /// Sends probes to (and listens for announcements on) another address than the standard
+    /// `239.255.255.250:3702` (or `[FF02::C]:3702` for IPv6).
+    ///
+    /// The group is joined only if the address is a multicast one, so probes can be sent to a
+    /// unicast address too, like a local fake device in tests.
+    pub fn multicast_addr(&mut self, multicast_addr: SocketAddr) -> &mut Self {
+        self.multicast_addr = Some(multicast_addr);
+        self
+    }
+
+    /// Ends the stream as soon as this many devices are found, without waiting for the whole
+    /// [`duration`](Self::duration).
+    pub fn max_devices(&mut self, max_devices: usize) -> &mut Self {
+        self.max_devices = Some(max_devices);
+        self
+    }
+
+    /// How many times the multicast probe is sent again after the first one, as UDP datagrams
+    /// may be lost. Defaults to 2, i.e. 3 probes in total.
+    ///
+    /// Probes which don't fit in the [`duration`](Self::duration) are not sent.
+    pub fn probe_retries(&mut self, probe_retries: u32) -> &mut Self {
+        self.probe_retries = probe_retries;
+        self
+    }
+
+    /// Delay between the probes, see [`probe_retries`](Self::probe_retries). Defaults to 500 ms.
+    pub fn probe_interval(&mut self, probe_interval: Duration) -> &mut Self {
+        self.probe_interval = probe_interval;
+        self
+    }
+
+    /// Probes only for devices of the given type, like `NetworkVideoTransmitter` (cameras and
+    /// encoders) or `tds:Device`. Names without a prefix are in the ONVIF network namespace
+    /// (`dn`).
+    ///
+    /// Devices which ignore the constraint and reply anyway are skipped.
+    pub fn device_type(&mut self, device_type: &str) -> &mut Self {
+        self.device_type = Some(if device_type.contains(':') {
+            device_type.to_string()
+        } else {
+            format!("dn:{}", device_type)
+        });
+        self
+    }
+
+    /// Probes only for devices with a scope starting with `scope`, like
+    /// `onvif://www.onvif.org/location/building1`.
+    ///
+    /// Devices which ignore the constraint and reply anyway are skipped unless one of their scopes
+    /// contains `scope`.
+    pub fn scope_filter(&mut self, scope: &str) -> &mut Self {
+        self.scope_filter = Some(scope.to_string());
+        self
+    }
+
     /// Discovers devices on a local network asynchronously using WS-discovery.
     ///
     /// Internally it sends a multicast probe and waits for responses for a specified amount of time.
     /// The result is a stream of discovered devices.
-    /// The stream is terminated after provided amount of time.
+    /// The stream is terminated after provided amount of time (even if no device responds), or
+    /// once [`max_devices`](Self::max_devices) devices are found.
+    ///
+    /// Devices which respond several times (e.g. on several network interfaces) are yielded once
+    /// per [`Device::endpoint_reference`], unless a later response has new URLs: then the device
+    /// is yielded again with the URLs of all its responses.
+    ///
+    /// Dropping the stream stops the discovery right away: the background tasks are aborted and
+    /// the sockets are closed, so it's fine to start and stop discoveries repeatedly. This also
+    /// makes the stream safe to use in `tokio::select!` against a shutdown signal.
     ///
     /// There are many different ways to iterate over and process the values in a `Stream`
     /// https://rust-lang.github.io/async-book/05_streams/02_iteration_and_concurrency.html
@@ -128,54 +320,175 @@ impl DiscoveryBuilder {
     /// };
     /// ```
     pub async fn run(&self) -> Result<impl Stream<Item = Device>, Error> {
-        let Self {
-            duration,
-            listen_address,
-        } = self;
+        let probe = Arc::new(self.build_probe());
+        let (sockets, tasks) = self.send_multicast_probes(&probe).await?;
 
-        let probe = Arc::new(build_probe());
-        let probe_xml = yaserde::ser::to_string(probe.as_ref()).map_err(Error::Serde)?;
+        Ok(spawn_device_stream(
+            sockets,
+            tasks,
+            probe,
+            self.duration,
+            self.max_devices,
+        ))
+    }
 
-        debug!("Probe XML: {}", probe_xml);
+    /// Like [`run`](Self::run), but yields every datagram received in response to the probe,
+    /// unparsed, with the address it comes from.
+    ///
+    /// Nothing is filtered out: responses to other probes, duplicates and the ones which fail
+    /// to parse are yielded too. This helps to find out why a device is missing from the results
+    /// of [`run`](Self::run), and to capture responses for regression tests.
+    /// [`max_devices`](Self::max_devices) doesn't apply.
+    pub async fn run_raw(&self) -> Result<impl Stream<Item = (SocketAddr, Vec<u8>)>, Error> {
+        let (sockets, tasks) = self.send_multicast_probes(&self.build_probe()).await?;
+
+        Ok(spawn_raw_stream(sockets, tasks, self.duration))
+    }
+
+    /// Sends the multicast probe on each listen address, see [`run`](Self::run), and returns
+    /// the sockets to receive the responses on along with the tasks resending the probe.
+    async fn send_multicast_probes(
+        &self,
+        probe: &probe::Envelope,
+    ) -> Result<(Vec<Arc<UdpSocket>>, Vec<JoinHandle<()>>), Error> {
+        let probe_xml = yaserde::ser::to_string(probe).map_err(Error::Serde)?;
 
-        let socket = {
-            const LOCAL_PORT: u16 = 0;
+        debug!("Probe XML: {}", probe_xml);
 
-            const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
-            const MULTI_PORT: u16 = 3702;
+        let listen_addresses = if self.all_interfaces {
+            interface_addresses()?
+        } else {
+            vec![self.listen_address]
+        };
 
-            let local_socket_addr = SocketAddr::new(*listen_address, LOCAL_PORT);
-            let multi_socket_addr = SocketAddr::new(IpAddr::V4(MULTI_IPV4_ADDR), MULTI_PORT);
+        let mut sockets = vec![];
+        let mut tasks = vec![];
 
-            let socket = UdpSocket::bind(local_socket_addr).await?;
+        for listen_address in listen_addresses {
+            let mut builder = self.clone();
+            builder.listen_address = listen_address;
 
-            match listen_address {
-                IpAddr::V4(addr) => socket.join_multicast_v4(MULTI_IPV4_ADDR, *addr)?,
-                IpAddr::V6(_) => return Err(Error::Unsupported("Discovery with IPv6".to_owned())),
+            match builder.send_multicast_probe(&probe_xml).await {
+                Ok((socket, resends)) => {
+                    sockets.push(socket);
+                    tasks.push(resends);
+                }
+                // With a single address, the error is worth reporting as is.
+                Err(e) if !self.all_interfaces => return Err(e),
+                Err(e) => debug!("Failed to probe on {listen_address}: {e}"),
             }
+        }
+
+        if sockets.is_empty() {
+            return Err(Error::Unsupported(
+                "No network interface to discover on".to_owned(),
+            ));
+        }
+
+        Ok((sockets, tasks))
+    }
+
+    /// Sends the probe directly to `addr` (usually port 3702 of a known device) and returns the
+    /// device which responds, or [`Error::Timeout`] if none does within the
+    /// [`duration`](Self::duration).
+    ///
+    /// For routed networks, where multicast doesn't reach the devices. The probe is repeated as
+    /// in [`run`](Self::run).
+    pub async fn probe_unicast(&self, addr: SocketAddr) -> Result<Device, Error> {
+        let probe = Arc::new(self.build_probe());
+        let probe_xml = yaserde::ser::to_string(probe.as_ref()).map_err(Error::Serde)?;
 
-            socket
-                .send_to(probe_xml.as_bytes(), multi_socket_addr)
-                .await?;
+        debug!("Probe XML: {}", probe_xml);
 
-            socket
+        const LOCAL_PORT: u16 = 0;
+        let local_addr = match (self.listen_address, addr) {
+            (IpAddr::V4(_), SocketAddr::V4(_)) | (IpAddr::V6(_), SocketAddr::V6(_)) => {
+                self.listen_address
+            }
+            (_, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (_, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
         };
 
-        let (device_sender, device_receiver) = channel(32);
-        let device_receiver = ReceiverStream::new(device_receiver);
+        let socket = Arc::new(UdpSocket::bind(SocketAddr::new(local_addr, LOCAL_PORT)).await?);
+        socket.send_to(probe_xml.as_bytes(), addr).await?;
 
-        let mut known_responses = HashSet::new();
+        let resends = self.spawn_probe_resends(socket.clone(), probe_xml, addr);
 
-        let produce_devices = async move {
-            while let Ok((xml, src)) = recv_string(&socket).await {
-                if !known_responses.insert(calculate_hash(&xml)) {
-                    debug!("Duplicate response from {src}, skipping ...");
-                    continue;
+        let mut devices =
+            spawn_device_stream(vec![socket], vec![resends], probe, self.duration, Some(1));
+
+        futures_util::StreamExt::next(&mut devices)
+            .await
+            .ok_or(Error::Timeout)
+    }
+
+    /// Discovers devices by sending a unicast probe to each host of an IPv4 network, like
+    /// `192.168.1.0/24`.
+    ///
+    /// It's a fallback for networks which drop multicast traffic. Probes are sent in small batches
+    /// and devices are yielded as they respond, the same way as in [`run`](Self::run). The
+    /// [`duration`](Self::duration) includes the time to send all probes. Networks larger than
+    /// [`MAX_UNICAST_SCAN_HOSTS`] are rejected.
+    pub async fn unicast_scan(&self, cidr: &str) -> Result<impl Stream<Item = Device>, Error> {
+        let hosts = parse_cidr(cidr)?;
+
+        let probe = Arc::new(self.build_probe());
+        let probe_xml = yaserde::ser::to_string(probe.as_ref()).map_err(Error::Serde)?;
+
+        debug!("Probe XML: {}", probe_xml);
+
+        if self.listen_address.is_ipv6() {
+            return Err(Error::Unsupported("Discovery with IPv6".to_owned()));
+        }
+
+        const LOCAL_PORT: u16 = 0;
+        let socket =
+            Arc::new(UdpSocket::bind(SocketAddr::new(self.listen_address, LOCAL_PORT)).await?);
+
+        let send_probes = {
+            let socket = socket.clone();
+
+            async move {
+                for batch in hosts.chunks(UNICAST_SCAN_BATCH) {
+                    for host in batch {
+                        let addr = SocketAddr::new(IpAddr::V4(*host), MULTI_PORT);
+                        if let Err(e) = socket.send_to(probe_xml.as_bytes(), addr).await {
+                            debug!("Failed to send probe to {addr}: {e}");
+                        }
+                    }
+
+                    // Give the network (and the responding devices) a break between batches.
+                    tokio::time::sleep(Duration::from_millis(10)).await;
                 }
+            }
+        };
+
+        let send_probes = spawn_with_timeout(self.duration, send_probes);
+
+        Ok(spawn_device_stream(
+            vec![socket],
+            vec![send_probes],
+            probe,
+            self.duration,
+            self.max_devices,
+        ))
+    }
 
-                debug!("Probe match XML: {}", xml,);
+    /// Passively listens for `Hello` and `Bye` messages which devices multicast when they join or
+    /// leave the network.
+    ///
+    /// Unlike [`run`](Self::run), no probe is sent and the stream doesn't end after
+    /// [`duration`](Self::duration): it lasts until it's dropped, which closes the socket.
+    pub async fn listen(&self) -> Result<impl Stream<Item = Announcement>, Error> {
+        let socket = self.bind_multicast(self.probe_destination().port()).await?;
+
+        let (announcement_sender, announcement_receiver) = channel(32);
 
-                let envelope = match yaserde::de::from_str::<probe_matches::Envelope>(&xml) {
+        let receive_announcements = tokio::spawn(async move {
+            while let Ok((xml, src)) = recv_string(&socket).await {
+                debug!("Announcement XML from {src}: {xml}");
+
+                let envelope = match yaserde::de::from_str::<announcement::Envelope>(&xml) {
                     Ok(envelope) => envelope,
                     Err(e) => {
                         debug!("Deserialization failed: {e}");
@@ -183,36 +496,442 @@ impl DiscoveryBuilder {
                     }
                 };
 
-                if envelope.header.relates_to != probe.header.message_id {
-                    debug!("Unrelated message");
+                if let Some(announcement) = announcement_from_envelope(envelope) {
+                    debug!("Announcement {announcement:?}");
+                    if announcement_sender.send(announcement).await.is_err() {
+                        // The stream has been dropped.
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(TaskStream {
+            receiver: announcement_receiver,
+            tasks: vec![receive_announcements],
+        })
+    }
+
+    /// Sends the probe to the multicast group, and schedules its repetitions.
+    async fn send_multicast_probe(
+        &self,
+        probe_xml: &str,
+    ) -> Result<(Arc<UdpSocket>, JoinHandle<()>), Error> {
+        const LOCAL_PORT: u16 = 0;
+
+        let socket = Arc::new(self.bind_multicast(LOCAL_PORT).await?);
+
+        socket
+            .send_to(probe_xml.as_bytes(), self.probe_destination())
+            .await?;
+
+        let resends = self.spawn_probe_resends(
+            socket.clone(),
+            probe_xml.to_owned(),
+            self.probe_destination(),
+        );
+
+        Ok((socket, resends))
+    }
+
+    /// Sends the probe again [`probe_retries`](Self::probe_retries) times, in the background.
+    fn spawn_probe_resends(
+        &self,
+        socket: Arc<UdpSocket>,
+        probe_xml: String,
+        destination: SocketAddr,
+    ) -> JoinHandle<()> {
+        let (retries, interval) = (self.probe_retries, self.probe_interval);
+
+        let resend_probes = async move {
+            for _ in 0..retries {
+                tokio::time::sleep(interval).await;
+                // Responses to the repeated probes are deduplicated like any other.
+                if let Err(e) = socket.send_to(probe_xml.as_bytes(), destination).await {
+                    debug!("Failed to resend probe: {e}");
+                }
+            }
+        };
+
+        spawn_with_timeout(self.duration, resend_probes)
+    }
+
+    /// Binds a socket to the listen address and joins the multicast group.
+    async fn bind_multicast(&self, port: u16) -> Result<UdpSocket, Error> {
+        match (self.listen_address, self.probe_destination().ip()) {
+            (IpAddr::V4(addr), IpAddr::V4(group)) => {
+                let socket = UdpSocket::bind(SocketAddr::new(addr.into(), port)).await?;
+                if group.is_multicast() {
+                    socket.join_multicast_v4(group, addr)?;
+                }
+                Ok(socket)
+            }
+            (IpAddr::V6(addr), IpAddr::V6(group)) => {
+                let socket =
+                    UdpSocket::bind(SocketAddrV6::new(addr, port, 0, self.scope_id)).await?;
+                if group.is_multicast() {
+                    socket.join_multicast_v6(&group, self.scope_id)?;
+                }
+                Ok(socket)
+            }
+            _ => Err(Error::InvalidArgument(
+                "Listen and multicast addresses are of different IP versions".to_string(),
+            )),
+        }
+    }
+
+    /// Multicast group (or any address set by [`multicast_addr`](Self::multicast_addr)) to send
+    /// probes to.
+    fn probe_destination(&self) -> SocketAddr {
+        if let Some(multicast_addr) = self.multicast_addr {
+            return multicast_addr;
+        }
+
+        match self.listen_address {
+            IpAddr::V4(_) => SocketAddr::new(MULTI_IPV4_ADDR.into(), MULTI_PORT),
+            IpAddr::V6(_) => {
+                SocketAddrV6::new(MULTI_IPV6_ADDR, MULTI_PORT, 0, self.scope_id).into()
+            }
+        }
+    }
+
+    fn build_probe(&self) -> probe::Envelope {
+        use probe::*;
+
+        Envelope {
+            header: Header {
+                message_id: format!("uuid:{}", uuid::Uuid::new_v4()),
+                action: "http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe".into(),
+                to: "urn:schemas-xmlsoap-org:ws:2005:04:discovery".into(),
+            },
+            body: Body {
+                probe: Probe {
+                    types: self.device_type.clone().unwrap_or_default(),
+                    scopes: self.scope_filter.clone(),
+                },
+            },
+        }
+    }
+}
+
+/// Sends a probe to `addr` using the default discovery settings, see
+/// [`DiscoveryBuilder::probe_unicast`].
+pub async fn probe_unicast(addr: SocketAddr) -> Result<Device, Error> {
+    DiscoveryBuilder::default().probe_unicast(addr).await
+}
+
+/// Stream of items produced by background tasks, which are aborted when the stream is dropped.
+struct TaskStream<T> {
+    receiver: Receiver<T>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<T> Stream for TaskStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for TaskStream<T> {
+    fn drop(&mut self) {
+        // Aborting drops the futures of the tasks, which closes the sockets they own.
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Runs `future` in the background for at most `duration`.
+fn spawn_with_timeout(
+    duration: Duration,
+    future: impl Future<Output = ()> + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let _ = timeout(duration, future).await;
+    })
+}
+
+/// Receives the matches of `probe` on `sockets` and yields the devices they describe, until
+/// `duration` elapses or `max_devices` devices are found.
+///
+/// `tasks` are the ones sending the probes, which are aborted along with the receiving ones once
+/// the stream is dropped.
+fn spawn_device_stream(
+    sockets: Vec<Arc<UdpSocket>>,
+    mut tasks: Vec<JoinHandle<()>>,
+    probe: Arc<probe::Envelope>,
+    duration: Duration,
+    max_devices: Option<usize>,
+) -> TaskStream<Device> {
+    let (device_sender, device_receiver) = channel(32);
+    let (response_sender, mut response_receiver) = channel(32);
+
+    for socket in sockets {
+        let response_sender = response_sender.clone();
+        let interface = socket
+            .local_addr()
+            .ok()
+            .map(|addr| addr.ip())
+            .filter(|ip| !ip.is_unspecified());
+
+        let receive_responses = async move {
+            while let Ok((xml, src)) = recv_string(&socket).await {
+                if response_sender.send((xml, src, interface)).await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        tasks.push(spawn_with_timeout(duration, receive_responses));
+    }
+
+    let mut known_responses = HashSet::new();
+    let mut known_devices = HashMap::new();
+
+    let produce_devices = async move {
+        let mut devices_found = 0;
+
+        while max_devices.map_or(true, |max| devices_found < max) {
+            let (xml, src, interface) = match response_receiver.recv().await {
+                Some(received) => received,
+                None => break,
+            };
+
+            if !known_responses.insert(calculate_hash(&xml)) {
+                debug!("Duplicate response from {src}, skipping ...");
+                continue;
+            }
+
+            debug!("Probe match XML: {}", xml,);
+
+            let envelope = match yaserde::de::from_str::<probe_matches::Envelope>(&xml) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    debug!("Deserialization failed: {e}");
                     continue;
                 }
+            };
+
+            if envelope.header.relates_to != probe.header.message_id {
+                debug!("Unrelated message");
+                continue;
+            }
+
+            if let Some(mut device) = device_from_envelope(envelope, &probe.body.probe) {
+                device.interface = interface;
+
+                let (device, is_new) = match merge_device(&mut known_devices, device) {
+                    Some(merged) => merged,
+                    None => {
+                        debug!("Duplicate device from {src}, skipping ...");
+                        continue;
+                    }
+                };
+
+                debug!("Found device {device:?}");
+                if is_new {
+                    devices_found += 1;
+                }
+                if device_sender.send(device).await.is_err() {
+                    // The user has dropped the stream (for example, after the first device
+                    // discovered), so there's no point to wait for more responses.
+                    break;
+                }
+            } else {
+                debug!("No devices found");
+            }
+        }
+    };
+
+    tasks.push(spawn_with_timeout(duration, produce_devices));
+
+    TaskStream {
+        receiver: device_receiver,
+        tasks,
+    }
+}
+
+/// Yields the datagrams received on `sockets` for `duration`, see [`DiscoveryBuilder::run_raw`].
+fn spawn_raw_stream(
+    sockets: Vec<Arc<UdpSocket>>,
+    mut tasks: Vec<JoinHandle<()>>,
+    duration: Duration,
+) -> TaskStream<(SocketAddr, Vec<u8>)> {
+    let (sender, receiver) = channel(32);
+
+    for socket in sockets {
+        let sender = sender.clone();
 
-                if let Some(device) = device_from_envelope(envelope) {
-                    debug!("Found device {device:?}");
-                    // It's ok to ignore the sending error as user can drop the receiver soon
-                    // (for example, after the first device discovered).
-                    let _ = device_sender.send(device).await;
-                } else {
-                    debug!("No devices found");
+        let receive_responses = async move {
+            while let Ok((bytes, src)) = recv_bytes(&socket).await {
+                if sender.send((src, bytes)).await.is_err() {
+                    break;
                 }
             }
         };
 
-        tokio::spawn(timeout(*duration, produce_devices));
+        tasks.push(spawn_with_timeout(duration, receive_responses));
+    }
+
+    TaskStream { receiver, tasks }
+}
+
+/// IPv4 addresses of the non-loopback network interfaces.
+fn interface_addresses() -> Result<Vec<IpAddr>, Error> {
+    Ok(if_addrs::get_if_addrs()?
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .map(|interface| interface.ip())
+        .filter(IpAddr::is_ipv4)
+        .collect())
+}
+
+/// Netmask of the local interface with the address `local_addr`.
+fn local_netmask(local_addr: IpAddr) -> Option<IpAddr> {
+    if_addrs::get_if_addrs()
+        .ok()?
+        .into_iter()
+        .find(|interface| interface.ip() == local_addr)
+        .map(|interface| match interface.addr {
+            if_addrs::IfAddr::V4(addr) => IpAddr::V4(addr.netmask),
+            if_addrs::IfAddr::V6(addr) => IpAddr::V6(addr.netmask),
+        })
+}
+
+/// See [`Device::best_service_url`], `netmask` is the one of `local_addr` if it's known.
+fn best_service_url<'a>(
+    urls: &'a [Url],
+    prefer_https: bool,
+    local_addr: IpAddr,
+    netmask: Option<IpAddr>,
+) -> Option<&'a Url> {
+    // Whether a host is in the network of `local_addr`, `None` if it's not reachable from there.
+    let in_network = |host: IpAddr| {
+        let in_network = match (host, local_addr, netmask) {
+            (IpAddr::V4(host), IpAddr::V4(local), Some(IpAddr::V4(mask))) => {
+                u32::from(host) & u32::from(mask) == u32::from(local) & u32::from(mask)
+            }
+            (IpAddr::V6(host), IpAddr::V6(local), Some(IpAddr::V6(mask))) => {
+                u128::from(host) & u128::from(mask) == u128::from(local) & u128::from(mask)
+            }
+            // The network is unknown, only the IP version can be checked.
+            (IpAddr::V4(_), IpAddr::V4(_), None) | (IpAddr::V6(_), IpAddr::V6(_), None) => {
+                return Some(false)
+            }
+            _ => return None,
+        };
+
+        Some(in_network).filter(|in_network| *in_network)
+    };
+
+    urls.iter()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter_map(|url| {
+            let in_network = match url.host()? {
+                url::Host::Domain(_) => false,
+                url::Host::Ipv4(host) if host.is_unspecified() => return None,
+                url::Host::Ipv4(host) => in_network(IpAddr::V4(host))?,
+                url::Host::Ipv6(host) if host.is_unspecified() => return None,
+                url::Host::Ipv6(host) => in_network(IpAddr::V6(host))?,
+            };
+            let preferred_scheme = (url.scheme() == "https") == prefer_https;
+
+            Some(((preferred_scheme, in_network), url))
+        })
+        // `max_by_key` returns the last maximum, the device order is kept by reversing.
+        .rev()
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, url)| url)
+}
+
+/// Deduplicates devices by their endpoint reference.
+///
+/// Returns the device to yield, and whether it's seen for the first time. A known device is
+/// returned again, with the union of the URLs, only if it has new URLs.
+fn merge_device(
+    known_devices: &mut HashMap<String, Device>,
+    device: Device,
+) -> Option<(Device, bool)> {
+    let reference = match &device.endpoint_reference {
+        Some(reference) => reference.clone(),
+        None => return Some((device, true)),
+    };
+
+    match known_devices.entry(reference) {
+        Entry::Occupied(mut known) => {
+            let known = known.get_mut();
+            let new_urls = device
+                .urls
+                .into_iter()
+                .filter(|url| !known.urls.contains(url))
+                .collect::<Vec<_>>();
+
+            if new_urls.is_empty() {
+                return None;
+            }
 
-        Ok(device_receiver)
+            known.urls.extend(new_urls);
+            Some((known.clone(), false))
+        }
+        Entry::Vacant(entry) => Some((entry.insert(device).clone(), true)),
     }
 }
 
-async fn recv_string(s: &UdpSocket) -> io::Result<(String, SocketAddr)> {
+/// Lists the hosts of an IPv4 network, without the network and broadcast addresses.
+fn parse_cidr(cidr: &str) -> Result<Vec<Ipv4Addr>, Error> {
+    let invalid = || Error::InvalidArgument(format!("Invalid IPv4 network: {}", cidr));
+
+    let (addr, prefix) = cidr.split_once('/').ok_or_else(invalid)?;
+    let addr = addr.parse::<IpAddr>().map_err(|_| invalid())?;
+    let prefix = prefix.parse::<u32>().map_err(|_| invalid())?;
+
+    let addr = match addr {
+        IpAddr::V4(addr) => u32::from(addr),
+        IpAddr::V6(_) => return Err(Error::Unsupported("Discovery with IPv6".to_owned())),
+    };
+
+    if prefix > 32 {
+        return Err(invalid());
+    }
+
+    let size = 1u64 << (32 - prefix);
+    if size > u64::from(MAX_UNICAST_SCAN_HOSTS) {
+        return Err(Error::InvalidArgument(format!(
+            "Network {} has {} hosts, at most {} can be scanned",
+            cidr, size, MAX_UNICAST_SCAN_HOSTS
+        )));
+    }
+
+    let network = addr & (u32::MAX << (32 - prefix));
+    let last = network + (size - 1) as u32;
+
+    // `/31` and `/32` networks have no network and broadcast addresses.
+    let hosts = if prefix >= 31 {
+        network..=last
+    } else {
+        network + 1..=last - 1
+    };
+
+    Ok(hosts.map(Ipv4Addr::from).collect())
+}
+
+async fn recv_bytes(s: &UdpSocket) -> io::Result<(Vec<u8>, SocketAddr)> {
     let mut buf = vec![0; 16 * 1024];
     let (len, src) = s.recv_from(&mut buf).await?;
+    buf.truncate(len);
+
+    Ok((buf, src))
+}
 
-    Ok((String::from_utf8_lossy(&buf[..len]).to_string(), src))
+async fn recv_string(s: &UdpSocket) -> io::Result<(String, SocketAddr)> {
+    let (bytes, src) = recv_bytes(s).await?;
+
+    Ok((String::from_utf8_lossy(&bytes).to_string(), src))
 }
 
-fn device_from_envelope(envelope: probe_matches::Envelope) -> Option<Device> {
+fn device_from_envelope(envelope: probe_matches::Envelope, probe: &probe::Probe) -> Option<Device> {
     let onvif_probe_match = envelope
         .body
         .probe_matches
@@ -222,24 +941,76 @@ fn device_from_envelope(envelope: probe_matches::Envelope) -> Option<Device> {
             probe_match
                 .find_in_scopes("onvif://www.onvif.org")
                 .is_some()
+                && matches_probe(probe_match, probe)
         })?;
 
-    let name = onvif_probe_match.name();
-    let urls = onvif_probe_match.x_addrs();
+    Some(device_from_probe_match(onvif_probe_match))
+}
+
+/// Checks the type and scope constraints of the probe, in case the device has ignored them.
+fn matches_probe(probe_match: &probe_matches::ProbeMatch, probe: &probe::Probe) -> bool {
+    // Devices may use other prefixes for the same namespaces, so compare only local names.
+    fn local_name(qname: &str) -> &str {
+        qname.rsplit(':').next().unwrap_or(qname)
+    }
+
+    let types = probe_match.types();
+    let types_match = probe.types.split_whitespace().all(|probe_type| {
+        types
+            .iter()
+            .any(|device_type| local_name(device_type) == local_name(probe_type))
+    });
 
-    Some(Device { name, urls })
+    let scopes_match = probe
+        .scopes
+        .iter()
+        .flat_map(|scopes| scopes.split_whitespace())
+        .all(|probe_scope| {
+            probe_match
+                .scopes
+                .split_whitespace()
+                .any(|device_scope| device_scope.contains(probe_scope))
+        });
+
+    types_match && scopes_match
 }
 
-fn build_probe() -> probe::Envelope {
-    use probe::*;
+fn announcement_from_envelope(envelope: announcement::Envelope) -> Option<Announcement> {
+    let (kind, probe_match) = match (envelope.body.hello, envelope.body.bye) {
+        (Some(hello), _) => (AnnouncementKind::Hello, hello),
+        (None, Some(bye)) => (AnnouncementKind::Bye, bye),
+        (None, None) => return None,
+    };
+
+    let app_sequence = envelope.header.app_sequence;
 
-    Envelope {
-        header: Header {
-            message_id: format!("uuid:{}", uuid::Uuid::new_v4()),
-            action: "http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe".into(),
-            to: "urn:schemas-xmlsoap-org:ws:2005:04:discovery".into(),
-        },
-        ..Default::default()
+    Some(Announcement {
+        kind,
+        device: device_from_probe_match(&probe_match),
+        instance_id: app_sequence.as_ref().map(|sequence| sequence.instance_id),
+        message_number: app_sequence
+            .as_ref()
+            .map(|sequence| sequence.message_number),
+    })
+}
+
+fn device_from_probe_match(probe_match: &probe_matches::ProbeMatch) -> Device {
+    Device {
+        endpoint_reference: probe_match
+            .endpoint_reference
+            .as_ref()
+            .map(|reference| reference.address.trim().to_string()),
+        name: probe_match.name(),
+        urls: probe_match.x_addrs(),
+        hardware: probe_match.hardware(),
+        location: probe_match.location(),
+        profiles: probe_match.profiles(),
+        scopes: probe_match
+            .scopes()
+            .iter()
+            .map(|scope| scope.to_string())
+            .collect(),
+        interface: None,
     }
 }
 
@@ -262,7 +1033,13 @@ fn test_xaddrs_extraction() {
                             <d:XAddrs>http://something.else</d:XAddrs>
                         </d:ProbeMatch>
                         <d:ProbeMatch>
-                            <d:Scopes>onvif://www.onvif.org/name/MyCamera2000</d:Scopes>
+                            <d:Scopes>
+                                onvif://www.onvif.org/name/MyCamera2000
+                                onvif://www.onvif.org/hardware/HW-2000
+                                onvif://www.onvif.org/location/Main%20Hall
+                                onvif://www.onvif.org/Profile/Streaming
+                                onvif://www.onvif.org/Profile/T
+                            </d:Scopes>
                             <d:XAddrs>{xaddrs}</d:XAddrs>
                         </d:ProbeMatch>
                     </d:ProbeMatches>
@@ -286,7 +1063,7 @@ fn test_xaddrs_extraction() {
         .iter()
         .filter_map(|xml| yaserde::de::from_str::<probe_matches::Envelope>(xml).ok())
         .filter(|envelope| envelope.header.relates_to == our_uuid)
-        .filter_map(device_from_envelope)
+        .filter_map(|envelope| device_from_envelope(envelope, &Default::default()))
         .collect::<Vec<_>>();
 
     assert_eq!(actual.len(), 1);
@@ -295,12 +1072,369 @@ fn test_xaddrs_extraction() {
     assert_eq!(
         actual,
         &[Device {
+            endpoint_reference: None,
             urls: vec![
                 Url::parse("http://addr_20").unwrap(),
                 Url::parse("http://addr_21").unwrap(),
                 Url::parse("http://addr_22").unwrap()
             ],
-            name: Some("MyCamera2000".to_string())
+            name: Some("MyCamera2000".to_string()),
+            hardware: Some("HW-2000".to_string()),
+            location: Some("Main Hall".to_string()),
+            profiles: vec!["Streaming".to_string(), "T".to_string()],
+            scopes: vec![
+                "onvif://www.onvif.org/name/MyCamera2000".to_string(),
+                "onvif://www.onvif.org/hardware/HW-2000".to_string(),
+                "onvif://www.onvif.org/location/Main%20Hall".to_string(),
+                "onvif://www.onvif.org/Profile/Streaming".to_string(),
+                "onvif://www.onvif.org/Profile/T".to_string(),
+            ],
+            interface: None,
         }]
     );
 }
+
+#[test]
+fn test_announcement_instance_id() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <SOAP-ENV:Envelope
+                    xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope"
+                    xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+                    xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery">
+            <SOAP-ENV:Header>
+                <wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Bye</wsa:Action>
+                <d:AppSequence InstanceId="42" MessageNumber="7"/>
+            </SOAP-ENV:Header>
+            <SOAP-ENV:Body>
+                <d:Bye>
+                    <d:Scopes>onvif://www.onvif.org/name/MyCamera2000</d:Scopes>
+                </d:Bye>
+            </SOAP-ENV:Body>
+        </SOAP-ENV:Envelope>
+        "#;
+
+    let envelope = yaserde::de::from_str::<announcement::Envelope>(xml).unwrap();
+    let announcement = announcement_from_envelope(envelope).unwrap();
+
+    assert_eq!(announcement.kind, AnnouncementKind::Bye);
+    assert_eq!(announcement.device.name, Some("MyCamera2000".to_string()));
+    assert_eq!(announcement.instance_id, Some(42));
+    assert_eq!(announcement.message_number, Some(7));
+}
+
+#[test]
+fn test_parse_cidr() {
+    let hosts = parse_cidr("192.168.1.77/30").unwrap();
+    assert_eq!(
+        hosts,
+        vec![
+            Ipv4Addr::new(192, 168, 1, 77),
+            Ipv4Addr::new(192, 168, 1, 78)
+        ]
+    );
+
+    assert_eq!(parse_cidr("10.0.0.0/24").unwrap().len(), 254);
+    assert_eq!(
+        parse_cidr("10.0.0.5/32").unwrap(),
+        vec![Ipv4Addr::new(10, 0, 0, 5)]
+    );
+
+    assert!(parse_cidr("10.0.0.0/8").is_err());
+    assert!(parse_cidr("10.0.0.0").is_err());
+    assert!(parse_cidr("10.0.0.0/33").is_err());
+}
+
+#[test]
+fn test_merge_device() {
+    let device = |urls: &[&str]| Device {
+        endpoint_reference: Some("urn:uuid:1".to_string()),
+        name: None,
+        urls: urls.iter().map(|url| Url::parse(url).unwrap()).collect(),
+        hardware: None,
+        location: None,
+        profiles: vec![],
+        scopes: vec![],
+        interface: None,
+    };
+
+    let mut known_devices = HashMap::new();
+
+    let (first, is_new) = merge_device(&mut known_devices, device(&["http://10.0.0.1/"])).unwrap();
+    assert!(is_new);
+    assert_eq!(first.urls.len(), 1);
+
+    assert!(merge_device(&mut known_devices, device(&["http://10.0.0.1/"])).is_none());
+
+    let (merged, is_new) = merge_device(
+        &mut known_devices,
+        device(&["http://10.0.0.1/", "http://192.168.0.1/"]),
+    )
+    .unwrap();
+    assert!(!is_new);
+    assert_eq!(
+        merged.urls,
+        vec![
+            Url::parse("http://10.0.0.1/").unwrap(),
+            Url::parse("http://192.168.0.1/").unwrap()
+        ]
+    );
+}
+
+#[test]
+fn test_probe_filter() {
+    let probe_match = probe_matches::ProbeMatch {
+        endpoint_reference: None,
+        types: "tdn:NetworkVideoTransmitter tds:Device".to_string(),
+        scopes: "onvif://www.onvif.org/name/Cam onvif://www.onvif.org/location/building1/floor2"
+            .to_string(),
+        x_addrs: "http://192.168.0.100/onvif/device_service".to_string(),
+    };
+
+    let mut builder = DiscoveryBuilder::default();
+    assert!(matches_probe(
+        &probe_match,
+        &builder.build_probe().body.probe
+    ));
+
+    builder
+        .device_type("NetworkVideoTransmitter")
+        .scope_filter("onvif://www.onvif.org/location/building1");
+    let probe = builder.build_probe();
+    assert_eq!(probe.body.probe.types, "dn:NetworkVideoTransmitter");
+    assert!(matches_probe(&probe_match, &probe.body.probe));
+
+    assert!(yaserde::ser::to_string(&probe)
+        .unwrap()
+        .contains("<d:Scopes>onvif://www.onvif.org/location/building1</d:Scopes>"));
+
+    builder.device_type("tds:NetworkVideoDisplay");
+    assert!(!matches_probe(
+        &probe_match,
+        &builder.build_probe().body.probe
+    ));
+
+    builder
+        .device_type("NetworkVideoTransmitter")
+        .scope_filter("building2");
+    assert!(!matches_probe(
+        &probe_match,
+        &builder.build_probe().body.probe
+    ));
+}
+
+#[test]
+fn test_multicast_addr() {
+    let mut builder = DiscoveryBuilder::default();
+    assert_eq!(
+        builder.probe_destination().to_string(),
+        "239.255.255.250:3702"
+    );
+
+    builder
+        .listen_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED))
+        .scope_id(3);
+    assert_eq!(builder.probe_destination().to_string(), "[ff02::c%3]:3702");
+}
+
+/// Answers the first probe it receives with a matching `ProbeMatches`.
+#[cfg(test)]
+async fn fake_responder() -> SocketAddr {
+    let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let responder_addr = responder.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (probe, src) = recv_string(&responder).await.unwrap();
+        let message_id = probe
+            .split("MessageID>")
+            .nth(1)
+            .and_then(|id| id.strip_suffix("</w:"))
+            .unwrap()
+            .to_string();
+
+        let probe_matches = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <s:Envelope
+                    xmlns:s="http://www.w3.org/2003/05/soap-envelope"
+                    xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+                    xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery">
+                <s:Header>
+                    <a:RelatesTo>{}</a:RelatesTo>
+                </s:Header>
+                <s:Body>
+                    <d:ProbeMatches>
+                        <d:ProbeMatch>
+                            <d:Scopes>onvif://www.onvif.org/name/Fake</d:Scopes>
+                            <d:XAddrs>http://127.0.0.1/onvif/device_service</d:XAddrs>
+                        </d:ProbeMatch>
+                    </d:ProbeMatches>
+                </s:Body>
+            </s:Envelope>"#,
+            message_id
+        );
+
+        responder
+            .send_to(probe_matches.as_bytes(), src)
+            .await
+            .unwrap();
+    });
+
+    responder_addr
+}
+
+#[tokio::test]
+async fn test_fake_responder() {
+    use futures_util::stream::StreamExt;
+
+    let responder_addr = fake_responder().await;
+
+    let devices = DiscoveryBuilder::default()
+        .listen_address(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .multicast_addr(responder_addr)
+        .duration(Duration::from_secs(2))
+        .max_devices(1)
+        .run()
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].name, Some("Fake".to_string()));
+    assert_eq!(devices[0].interface, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+}
+
+#[tokio::test]
+async fn test_run_raw() {
+    use futures_util::stream::StreamExt;
+
+    let responder_addr = fake_responder().await;
+
+    let responses = DiscoveryBuilder::default()
+        .listen_address(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .multicast_addr(responder_addr)
+        .duration(Duration::from_secs(1))
+        .run_raw()
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].0, responder_addr);
+
+    let xml = String::from_utf8(responses[0].1.clone()).unwrap();
+    assert!(xml.contains("<d:Scopes>onvif://www.onvif.org/name/Fake</d:Scopes>"));
+}
+
+#[tokio::test]
+async fn test_probe_unicast() {
+    let device = probe_unicast(fake_responder().await).await.unwrap();
+    assert_eq!(device.name, Some("Fake".to_string()));
+
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let result = DiscoveryBuilder::default()
+        .duration(Duration::from_millis(200))
+        .probe_unicast(silent.local_addr().unwrap())
+        .await;
+    assert!(matches!(result, Err(Error::Timeout)));
+}
+
+#[tokio::test]
+async fn test_duration() {
+    use futures_util::stream::StreamExt;
+
+    let started = std::time::Instant::now();
+
+    let devices = DiscoveryBuilder::default()
+        .duration(Duration::from_millis(200))
+        .unicast_scan("127.0.0.1/32")
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+
+    assert!(devices.is_empty());
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_drop_releases_socket() {
+    use futures_util::stream::StreamExt;
+
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    let mut devices = DiscoveryBuilder::default()
+        .listen_address(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .multicast_addr(silent.local_addr().unwrap())
+        .duration(Duration::from_secs(60))
+        .run()
+        .await
+        .unwrap();
+
+    let (_, discovery_addr) = recv_string(&silent).await.unwrap();
+
+    // Still discovering, so the port is taken.
+    assert!(timeout(Duration::from_millis(100), devices.next())
+        .await
+        .is_err());
+    assert!(UdpSocket::bind(discovery_addr).await.is_err());
+
+    drop(devices);
+
+    // The aborted tasks are dropped the next time the runtime gets to them.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    UdpSocket::bind(discovery_addr).await.unwrap();
+}
+
+#[test]
+fn test_best_service_url() {
+    let urls: Vec<Url> = [
+        "http://192.168.1.10/onvif/device_service",
+        "https://10.0.0.10/onvif/device_service",
+        "https://camera.local/onvif/device_service",
+        "https://[fd00::10]/onvif/device_service",
+        "https://192.168.1.10/onvif/device_service",
+    ]
+    .iter()
+    .map(|url| url.parse().unwrap())
+    .collect();
+
+    let local: IpAddr = "192.168.1.2".parse().unwrap();
+    let mask: IpAddr = "255.255.255.0".parse().unwrap();
+    let best = |urls: &[Url], prefer_https, local, netmask| {
+        best_service_url(urls, prefer_https, local, netmask).map(Url::as_str)
+    };
+
+    assert_eq!(
+        best(&urls, true, local, Some(mask)),
+        Some("https://192.168.1.10/onvif/device_service")
+    );
+    assert_eq!(
+        best(&urls, false, local, Some(mask)),
+        Some("http://192.168.1.10/onvif/device_service")
+    );
+    // 10.0.0.10 is out of the network, the host name may resolve to anything.
+    assert_eq!(
+        best(&urls[1..4], true, local, Some(mask)),
+        Some("https://camera.local/onvif/device_service")
+    );
+    // Falls back to HTTP.
+    assert_eq!(
+        best(&urls[..2], true, local, Some(mask)),
+        Some("http://192.168.1.10/onvif/device_service")
+    );
+    assert_eq!(best(&urls[1..2], true, local, Some(mask)), None);
+    assert_eq!(
+        best(&urls[1..2], true, local, None),
+        Some("https://10.0.0.10/onvif/device_service")
+    );
+    assert_eq!(
+        best(
+            &urls,
+            true,
+            "fd00::2".parse().unwrap(),
+            Some("ffff:ffff:ffff:ffff::".parse().unwrap())
+        ),
+        Some("https://[fd00::10]/onvif/device_service")
+    );
+}
@@ -0,0 +1,783 @@
+use crate::soap::client::Client;
+use futures_core::Stream;
+use schema::transport::{self, Transport};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+use url::Url;
+use xmltree::{Element, XMLNode};
+
+const EVENTS_NAMESPACE: &str = "http://www.onvif.org/ver10/events/wsdl";
+const NOTIFICATION_NAMESPACE: &str = "http://docs.oasis-open.org/wsn/b-2";
+const TOPICS_NAMESPACE: &str = "http://www.onvif.org/ver10/topics";
+const CONCRETE_SET_DIALECT: &str = "http://www.onvif.org/ver10/tev/topicExpression/ConcreteSet";
+
+/// Notification decoded from a `wsnt:NotificationMessage` element.
+///
+/// Generated `schema::event` types drop the content of the message, so notifications are parsed
+/// from the raw XML instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationMessage {
+    /// Topic of the notification, e.g. `tns1:RuleEngine/CellMotionDetector/Motion`.
+    pub topic: String,
+    pub utc_time: Option<String>,
+    /// `Initialized`, `Changed` or `Deleted` for property events.
+    pub property_operation: Option<String>,
+    pub source: Vec<SimpleItem>,
+    pub key: Vec<SimpleItem>,
+    pub data: Vec<SimpleItem>,
+    pub data_elements: Vec<ElementItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimpleItem {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementItem {
+    pub name: String,
+    pub element: Element,
+}
+
+/// Object reported by an analytics (Profile M) object detection event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectDetection {
+    pub object_id: Option<String>,
+    /// Class of the object, like `Human`, `Vehicle` or `Face`.
+    pub class: Option<String>,
+    /// Confidence of the classification in the `0.0..=1.0` range.
+    pub likelihood: Option<f64>,
+    pub bounding_box: Option<BoundingBox>,
+}
+
+/// Bounding box in the normalized `-1.0..=1.0` coordinate space of the video source.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoundingBox {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl NotificationMessage {
+    /// Finds a `SimpleItem` by name in the source, key and data of the message.
+    pub fn item(&self, name: &str) -> Option<&str> {
+        self.source
+            .iter()
+            .chain(&self.key)
+            .chain(&self.data)
+            .find(|item| item.name == name)
+            .map(|item| item.value.as_str())
+    }
+
+    /// Decodes the message as an object detection event.
+    ///
+    /// Returns `None` if the message carries neither an object class nor an object description.
+    pub fn object_detection(&self) -> Option<ObjectDetection> {
+        let mut detection = ObjectDetection {
+            object_id: self.item("ObjectId").map(str::to_string),
+            class: ["ClassTypes", "ObjectType", "Class", "Type"]
+                .iter()
+                .find_map(|name| self.item(name))
+                .map(str::to_string),
+            likelihood: ["Likelihood", "Confidence"]
+                .iter()
+                .find_map(|name| self.item(name))
+                .and_then(|value| value.parse::<f64>().ok()),
+            bounding_box: None,
+        };
+
+        let object = self.data_elements.iter().find_map(|item| {
+            if item.element.name == "Object" {
+                Some(&item.element)
+            } else {
+                child(&item.element, "Object")
+            }
+        });
+
+        if let Some(object) = object {
+            if detection.object_id.is_none() {
+                detection.object_id = object.attributes.get("ObjectId").cloned();
+            }
+
+            let appearance = child(object, "Appearance");
+
+            detection.bounding_box = appearance
+                .and_then(|appearance| child(appearance, "Shape"))
+                .and_then(|shape| child(shape, "BoundingBox"))
+                .and_then(parse_bounding_box);
+
+            if let Some(class) = appearance.and_then(|appearance| child(appearance, "Class")) {
+                let (class_type, likelihood) = parse_class(class);
+                detection.class = detection.class.or(class_type);
+                detection.likelihood = detection.likelihood.or(likelihood);
+            }
+        } else if detection.class.is_none() {
+            return None;
+        }
+
+        Some(detection)
+    }
+}
+
+/// Notification classified by its topic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Motion alarm or motion detector rule state.
+    Motion {
+        source: Option<String>,
+        active: bool,
+    },
+    /// Tampering or global scene change.
+    Tamper {
+        source: Option<String>,
+        active: bool,
+    },
+    /// Object reported by an analytics module.
+    ObjectDetected(ObjectDetection),
+    /// State of a digital input or a relay output.
+    IoPort { token: Option<String>, active: bool },
+    /// Any other notification.
+    Other(NotificationMessage),
+}
+
+impl From<NotificationMessage> for Event {
+    fn from(message: NotificationMessage) -> Self {
+        let topic = message.topic.as_str();
+        let source = message.source.first().map(|item| item.value.clone());
+        let state = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| message.item(name))
+                .map(is_active)
+        };
+
+        if topic.contains("Motion") {
+            if let Some(active) = state(&["IsMotion", "State"]) {
+                return Self::Motion { source, active };
+            }
+        } else if topic.contains("Tamper") || topic.contains("GlobalSceneChange") {
+            if let Some(active) = state(&["IsTamper", "State"]) {
+                return Self::Tamper { source, active };
+            }
+        } else if topic.contains("DigitalInput") || topic.contains("Relay") {
+            if let Some(active) = state(&["LogicalState", "State"]) {
+                return Self::IoPort {
+                    token: source,
+                    active,
+                };
+            }
+        } else if topic.contains("ObjectDetection") {
+            if let Some(detection) = message.object_detection() {
+                return Self::ObjectDetected(detection);
+            }
+        }
+
+        Self::Other(message)
+    }
+}
+
+fn is_active(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "active" | "on"
+    )
+}
+
+/// Settings of [`event_stream`].
+#[derive(Debug, Clone)]
+pub struct EventStreamOptions {
+    /// Topic expression to subscribe to, see [`PullPointSubscription::create`]. All topics by
+    /// default.
+    pub filter: Option<String>,
+    /// Maximum number of messages returned by one `PullMessages` request.
+    pub message_limit: u32,
+    /// How long one `PullMessages` request waits for messages. It must be shorter than the client
+    /// timeout.
+    pub timeout: Duration,
+    /// Termination time of the subscription, which is renewed when half of it is left.
+    pub termination: Duration,
+}
+
+impl Default for EventStreamOptions {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            message_limit: 10,
+            timeout: Duration::from_secs(3),
+            termination: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Pull point subscription created by `CreatePullPointSubscription`.
+///
+/// Requests are built and parsed by hand as `schema::event` types drop the subscription address
+/// and the notification messages.
+#[derive(Clone)]
+pub struct PullPointSubscription {
+    /// Client for the subscription manager endpoint.
+    client: Client,
+    /// Client for the event service, to recreate the subscription.
+    events: Client,
+    filter: Option<String>,
+}
+
+impl PullPointSubscription {
+    /// Creates a pull point on the event service.
+    ///
+    /// `filter` is a topic expression in the ONVIF `ConcreteSet` dialect, like
+    /// `tns1:RuleEngine//.` or `tns1:VideoSource/MotionAlarm`. The subscription is terminated by
+    /// the device after `termination` unless it's renewed.
+    pub async fn create(
+        events: &Client,
+        filter: Option<&str>,
+        termination: Duration,
+    ) -> Result<Self, transport::Error> {
+        let filter_element = filter
+            .map(|filter| {
+                format!(
+                    r#"<tev:Filter><wsnt:TopicExpression Dialect="{}">{}</wsnt:TopicExpression></tev:Filter>"#,
+                    CONCRETE_SET_DIALECT,
+                    escape(filter)
+                )
+            })
+            .unwrap_or_default();
+
+        let request = format!(
+            r#"<tev:CreatePullPointSubscription xmlns:tev="{}" xmlns:wsnt="{}" xmlns:tns1="{}">{}<tev:InitialTerminationTime>{}</tev:InitialTerminationTime></tev:CreatePullPointSubscription>"#,
+            EVENTS_NAMESPACE,
+            NOTIFICATION_NAMESPACE,
+            TOPICS_NAMESPACE,
+            filter_element,
+            xs_duration(termination)
+        );
+
+        let xml = events.request(&request).await?;
+        let response = parse_response(&xml)?;
+
+        let address = child(&response, "SubscriptionReference")
+            .and_then(|reference| child(reference, "Address"))
+            .and_then(text)
+            .ok_or_else(|| {
+                transport::Error::deserialization(&xml, "Subscription address not found")
+            })?;
+
+        let address =
+            Url::parse(&address).map_err(|e| transport::Error::deserialization(&xml, e))?;
+
+        debug!("Created pull point subscription {}", address);
+
+        // Devices with one endpoint for all their subscriptions tell them apart by the `To` header.
+        Ok(Self {
+            client: events.with_uri(&address),
+            events: events.clone(),
+            filter: filter.map(str::to_string),
+        })
+    }
+
+    /// Yields the events of the subscription as they come, see [`event_stream`].
+    ///
+    /// The subscription is renewed on the first poll, and recreated with the same filter if the
+    /// device drops it. `options.filter` is ignored.
+    pub fn into_event_stream(
+        self,
+        options: EventStreamOptions,
+    ) -> impl Stream<Item = Result<Event, transport::Error>> {
+        let options = EventStreamOptions {
+            filter: self.filter.clone(),
+            ..options
+        };
+
+        EventPuller::new(self.events.clone(), options, Some(self)).into_stream()
+    }
+
+    /// Address of the subscription manager.
+    pub fn address(&self) -> &Url {
+        self.client.uri()
+    }
+
+    /// Waits up to `timeout` for notifications and returns at most `message_limit` of them.
+    ///
+    /// `timeout` must be shorter than the client timeout, otherwise the HTTP request times out
+    /// before the device responds.
+    pub async fn pull_messages(
+        &self,
+        timeout: Duration,
+        message_limit: u32,
+    ) -> Result<Vec<NotificationMessage>, transport::Error> {
+        let request = format!(
+            r#"<tev:PullMessages xmlns:tev="{}"><tev:Timeout>{}</tev:Timeout><tev:MessageLimit>{}</tev:MessageLimit></tev:PullMessages>"#,
+            EVENTS_NAMESPACE,
+            xs_duration(timeout),
+            message_limit
+        );
+
+        let response = parse_response(&self.client.request(&request).await?)?;
+
+        Ok(parse_notification_messages(&response))
+    }
+
+    /// Like [`PullPointSubscription::pull_messages`], with the messages classified by their topic.
+    pub async fn pull_events(
+        &self,
+        timeout: Duration,
+        message_limit: u32,
+    ) -> Result<Vec<Event>, transport::Error> {
+        Ok(self
+            .pull_messages(timeout, message_limit)
+            .await?
+            .into_iter()
+            .map(Event::from)
+            .collect())
+    }
+
+    /// Postpones the termination of the subscription to `termination` from now.
+    pub async fn renew(&self, termination: Duration) -> Result<(), transport::Error> {
+        let request = format!(
+            r#"<wsnt:Renew xmlns:wsnt="{}"><wsnt:TerminationTime>{}</wsnt:TerminationTime></wsnt:Renew>"#,
+            NOTIFICATION_NAMESPACE,
+            xs_duration(termination)
+        );
+
+        self.client.request(&request).await?;
+
+        Ok(())
+    }
+
+    /// Terminates the subscription.
+    pub async fn unsubscribe(&self) -> Result<(), transport::Error> {
+        let request = format!(
+            r#"<wsnt:Unsubscribe xmlns:wsnt="{}"/>"#,
+            NOTIFICATION_NAMESPACE
+        );
+
+        self.client.request(&request).await?;
+
+        Ok(())
+    }
+}
+
+/// Subscribes to the events of the event service and yields them as they come.
+///
+/// The pull point is created on the first poll, and the next `PullMessages` request is made only
+/// when all the events of the previous one are consumed. The subscription is renewed (or
+/// recreated if the device has dropped it, e.g. after a restart) as needed, and it's terminated
+/// when the stream is dropped. The stream ends after the first error.
+pub fn event_stream(
+    events: Client,
+    options: EventStreamOptions,
+) -> impl Stream<Item = Result<Event, transport::Error>> {
+    EventPuller::new(events, options, None).into_stream()
+}
+
+/// State of [`event_stream`].
+struct EventPuller {
+    events: Client,
+    options: EventStreamOptions,
+    subscription: Option<PullPointSubscription>,
+    renew_at: Instant,
+    pending: VecDeque<Event>,
+    done: bool,
+}
+
+impl EventPuller {
+    fn new(
+        events: Client,
+        options: EventStreamOptions,
+        subscription: Option<PullPointSubscription>,
+    ) -> Self {
+        Self {
+            events,
+            options,
+            subscription,
+            renew_at: Instant::now(),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn into_stream(self) -> impl Stream<Item = Result<Event, transport::Error>> {
+        futures_util::stream::unfold(self, |mut puller| async move {
+            let event = puller.next().await?;
+            Some((event, puller))
+        })
+    }
+
+    async fn next(&mut self) -> Option<Result<Event, transport::Error>> {
+        while !self.done {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            if let Err(e) = self.pull().await {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        None
+    }
+
+    async fn pull(&mut self) -> Result<(), transport::Error> {
+        let subscription = self.subscription().await?;
+
+        let events = match subscription
+            .pull_events(self.options.timeout, self.options.message_limit)
+            .await
+        {
+            Ok(events) => events,
+            Err(e) => {
+                // The device may have dropped the subscription (after a restart, etc).
+                debug!("Failed to pull messages: {}. Resubscribing ...", e);
+                self.subscription = None;
+                self.subscription()
+                    .await?
+                    .pull_events(self.options.timeout, self.options.message_limit)
+                    .await?
+            }
+        };
+
+        self.pending.extend(events);
+
+        Ok(())
+    }
+
+    /// Returns the current subscription, renewing or recreating it if needed.
+    async fn subscription(&mut self) -> Result<PullPointSubscription, transport::Error> {
+        if let Some(subscription) = &self.subscription {
+            if Instant::now() < self.renew_at {
+                return Ok(subscription.clone());
+            }
+
+            match subscription.renew(self.options.termination).await {
+                Ok(()) => {
+                    self.renew_at = Instant::now() + self.options.termination / 2;
+                    return Ok(subscription.clone());
+                }
+                Err(e) => debug!("Failed to renew subscription: {}. Resubscribing ...", e),
+            }
+        }
+
+        let subscription = PullPointSubscription::create(
+            &self.events,
+            self.options.filter.as_deref(),
+            self.options.termination,
+        )
+        .await?;
+
+        self.subscription = Some(subscription.clone());
+        self.renew_at = Instant::now() + self.options.termination / 2;
+
+        Ok(subscription)
+    }
+}
+
+impl Drop for EventPuller {
+    fn drop(&mut self) {
+        // Best effort: the device terminates the subscription by itself anyway.
+        if let Some(subscription) = self.subscription.take() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move {
+                    if let Err(e) = subscription.unsubscribe().await {
+                        debug!("Failed to unsubscribe: {}", e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn parse_response(response: &str) -> Result<Element, transport::Error> {
+    Element::parse(response.as_bytes()).map_err(|e| transport::Error::deserialization(response, e))
+}
+
+/// Formats `duration` as `xs:duration`, of at least a second.
+fn xs_duration(duration: Duration) -> String {
+    schema::common::Duration::from(duration.max(Duration::from_secs(1))).to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Parses all `wsnt:NotificationMessage` children of `root`, e.g. of `PullMessagesResponse`.
+pub fn parse_notification_messages(root: &Element) -> Vec<NotificationMessage> {
+    children(root, "NotificationMessage")
+        .map(parse_notification_message)
+        .collect()
+}
+
+fn parse_notification_message(element: &Element) -> NotificationMessage {
+    let topic = child(element, "Topic")
+        .and_then(|topic| topic.get_text())
+        .map(|text| text.trim().to_string())
+        .unwrap_or_default();
+
+    // `wsnt:Message` wraps the actual `tt:Message`.
+    let message = child(element, "Message").and_then(|message| child(message, "Message"));
+
+    let simple_items = |name: &str| -> Vec<SimpleItem> {
+        message
+            .and_then(|message| child(message, name))
+            .map(|items| {
+                children(items, "SimpleItem")
+                    .filter_map(|item| {
+                        Some(SimpleItem {
+                            name: item.attributes.get("Name")?.clone(),
+                            value: item.attributes.get("Value").cloned().unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let data_elements = message
+        .and_then(|message| child(message, "Data"))
+        .map(|data| {
+            children(data, "ElementItem")
+                .filter_map(|item| {
+                    Some(ElementItem {
+                        name: item.attributes.get("Name")?.clone(),
+                        element: first_child(item)?.clone(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    NotificationMessage {
+        topic,
+        utc_time: message.and_then(|message| message.attributes.get("UtcTime").cloned()),
+        property_operation: message
+            .and_then(|message| message.attributes.get("PropertyOperation").cloned()),
+        source: simple_items("Source"),
+        key: simple_items("Key"),
+        data: simple_items("Data"),
+        data_elements,
+    }
+}
+
+fn parse_bounding_box(element: &Element) -> Option<BoundingBox> {
+    let attribute = |name: &str| element.attributes.get(name)?.parse::<f64>().ok();
+
+    Some(BoundingBox {
+        left: attribute("left")?,
+        top: attribute("top")?,
+        right: attribute("right")?,
+        bottom: attribute("bottom")?,
+    })
+}
+
+fn parse_class(class: &Element) -> (Option<String>, Option<f64>) {
+    // ONVIF 2.x: `<tt:ClassCandidate><tt:Type>Human</tt:Type><tt:Likelihood>0.9</tt:Likelihood>`.
+    if let Some(candidate) = children(class, "ClassCandidate").next() {
+        return (
+            child(candidate, "Type").and_then(text),
+            child(candidate, "Likelihood")
+                .and_then(text)
+                .and_then(|value| value.parse::<f64>().ok()),
+        );
+    }
+
+    // ONVIF 21.06+: `<tt:Type Likelihood="0.9">Human</tt:Type>`.
+    match children(class, "Type").next() {
+        Some(class_type) => (
+            text(class_type),
+            class_type
+                .attributes
+                .get("Likelihood")
+                .and_then(|value| value.parse::<f64>().ok()),
+        ),
+        None => (None, None),
+    }
+}
+
+fn text(element: &Element) -> Option<String> {
+    element.get_text().map(|text| text.trim().to_string())
+}
+
+fn child<'a>(element: &'a Element, name: &str) -> Option<&'a Element> {
+    element.children.iter().find_map(|node| match node {
+        XMLNode::Element(child) if child.name == name => Some(child),
+        _ => None,
+    })
+}
+
+fn children<'a>(element: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+    element.children.iter().filter_map(move |node| match node {
+        XMLNode::Element(child) if child.name == name => Some(child),
+        _ => None,
+    })
+}
+
+fn first_child(element: &Element) -> Option<&Element> {
+    element.children.iter().find_map(|node| match node {
+        XMLNode::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> Vec<NotificationMessage> {
+        parse_notification_messages(&Element::parse(xml.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn motion_notification() {
+        let messages = parse(
+            r#"
+            <tev:PullMessagesResponse
+                    xmlns:tev="http://www.onvif.org/ver10/events/wsdl"
+                    xmlns:wsnt="http://docs.oasis-open.org/wsn/b-2"
+                    xmlns:tns1="http://www.onvif.org/ver10/topics"
+                    xmlns:tt="http://www.onvif.org/ver10/schema">
+                <tev:CurrentTime>2021-01-01T10:00:00Z</tev:CurrentTime>
+                <tev:TerminationTime>2021-01-01T10:01:00Z</tev:TerminationTime>
+                <wsnt:NotificationMessage>
+                    <wsnt:Topic Dialect="http://www.onvif.org/ver10/tev/topicExpression/ConcreteSet">
+                        tns1:RuleEngine/CellMotionDetector/Motion
+                    </wsnt:Topic>
+                    <wsnt:Message>
+                        <tt:Message UtcTime="2021-01-01T10:00:00Z" PropertyOperation="Changed">
+                            <tt:Source>
+                                <tt:SimpleItem Name="VideoSourceConfigurationToken" Value="VSC_1"/>
+                                <tt:SimpleItem Name="Rule" Value="MyMotionDetectorRule"/>
+                            </tt:Source>
+                            <tt:Data>
+                                <tt:SimpleItem Name="IsMotion" Value="true"/>
+                            </tt:Data>
+                        </tt:Message>
+                    </wsnt:Message>
+                </wsnt:NotificationMessage>
+            </tev:PullMessagesResponse>
+            "#,
+        );
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].topic,
+            "tns1:RuleEngine/CellMotionDetector/Motion"
+        );
+        assert_eq!(messages[0].property_operation.as_deref(), Some("Changed"));
+        assert_eq!(messages[0].item("Rule"), Some("MyMotionDetectorRule"));
+        assert_eq!(messages[0].item("IsMotion"), Some("true"));
+        assert_eq!(messages[0].object_detection(), None);
+    }
+
+    #[test]
+    fn classified_events() {
+        let message = |topic: &str, name: &str, value: &str| NotificationMessage {
+            topic: topic.to_string(),
+            utc_time: None,
+            property_operation: None,
+            source: vec![SimpleItem {
+                name: "Source".to_string(),
+                value: "VS_1".to_string(),
+            }],
+            key: vec![],
+            data: vec![SimpleItem {
+                name: name.to_string(),
+                value: value.to_string(),
+            }],
+            data_elements: vec![],
+        };
+
+        assert_eq!(
+            Event::from(message("tns1:VideoSource/MotionAlarm", "State", "true")),
+            Event::Motion {
+                source: Some("VS_1".to_string()),
+                active: true
+            }
+        );
+        assert_eq!(
+            Event::from(message(
+                "tns1:VideoSource/GlobalSceneChange/ImagingService",
+                "State",
+                "false"
+            )),
+            Event::Tamper {
+                source: Some("VS_1".to_string()),
+                active: false
+            }
+        );
+        assert_eq!(
+            Event::from(message(
+                "tns1:Device/Trigger/DigitalInput",
+                "LogicalState",
+                "true"
+            )),
+            Event::IoPort {
+                token: Some("VS_1".to_string()),
+                active: true
+            }
+        );
+
+        let other = message("tns1:Monitoring/ProcessorUsage", "Value", "42");
+        assert_eq!(Event::from(other.clone()), Event::Other(other));
+    }
+
+    #[test]
+    fn object_detection_notification() {
+        let messages = parse(
+            r#"
+            <tev:PullMessagesResponse
+                    xmlns:tev="http://www.onvif.org/ver10/events/wsdl"
+                    xmlns:wsnt="http://docs.oasis-open.org/wsn/b-2"
+                    xmlns:tt="http://www.onvif.org/ver10/schema">
+                <wsnt:NotificationMessage>
+                    <wsnt:Topic>tns1:RuleEngine/ObjectDetection/Object</wsnt:Topic>
+                    <wsnt:Message>
+                        <tt:Message UtcTime="2021-01-01T10:00:00Z">
+                            <tt:Source>
+                                <tt:SimpleItem Name="VideoSource" Value="VS_1"/>
+                            </tt:Source>
+                            <tt:Data>
+                                <tt:SimpleItem Name="ObjectId" Value="42"/>
+                                <tt:ElementItem Name="Object">
+                                    <tt:Object ObjectId="42">
+                                        <tt:Appearance>
+                                            <tt:Shape>
+                                                <tt:BoundingBox left="-0.5" top="0.5" right="0.25" bottom="-0.75"/>
+                                            </tt:Shape>
+                                            <tt:Class>
+                                                <tt:Type Likelihood="0.87">Human</tt:Type>
+                                            </tt:Class>
+                                        </tt:Appearance>
+                                    </tt:Object>
+                                </tt:ElementItem>
+                            </tt:Data>
+                        </tt:Message>
+                    </wsnt:Message>
+                </wsnt:NotificationMessage>
+            </tev:PullMessagesResponse>
+            "#,
+        );
+
+        assert_eq!(
+            messages[0].object_detection(),
+            Some(ObjectDetection {
+                object_id: Some("42".to_string()),
+                class: Some("Human".to_string()),
+                likelihood: Some(0.87),
+                bounding_box: Some(BoundingBox {
+                    left: -0.5,
+                    top: 0.5,
+                    right: 0.25,
+                    bottom: -0.75,
+                }),
+            })
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::utils::fault::{is_invalid_argument, is_not_supported};
+use schema::{
+    imaging,
+    onvif::{ImagingOptions20, ImagingSettings20, ReferenceToken},
+    transport::{self, Transport},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The device rejected the settings, e.g. a value is out of the range reported by
+    /// [`get_options`] or the video source doesn't have the setting.
+    #[error("Invalid imaging settings: {0}")]
+    InvalidSettings(String),
+
+    /// The video source has no imaging settings.
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] transport::Error),
+}
+
+/// Fetches the imaging settings (brightness, contrast, focus, white balance, etc) of the video
+/// source.
+pub async fn get_imaging_settings<T: Transport>(
+    transport: &T,
+    video_source_token: &str,
+) -> Result<ImagingSettings20, Error> {
+    Ok(imaging::get_imaging_settings(
+        transport,
+        &imaging::GetImagingSettings {
+            video_source_token: ReferenceToken(video_source_token.to_string()),
+        },
+    )
+    .await
+    .map_err(imaging_error)?
+    .imaging_settings)
+}
+
+/// Changes the imaging settings of the video source.
+///
+/// Settings left to `None` are not changed. With `force_persistence`, the settings survive a
+/// reboot of the device; otherwise the device decides whether they do.
+pub async fn set_imaging_settings<T: Transport>(
+    transport: &T,
+    video_source_token: &str,
+    settings: ImagingSettings20,
+    force_persistence: bool,
+) -> Result<(), Error> {
+    imaging::set_imaging_settings(
+        transport,
+        &imaging::SetImagingSettings {
+            video_source_token: ReferenceToken(video_source_token.to_string()),
+            imaging_settings: settings,
+            force_persistence,
+        },
+    )
+    .await
+    .map_err(imaging_error)?;
+
+    Ok(())
+}
+
+/// Fetches the valid ranges and modes of the imaging settings of the video source.
+pub async fn get_options<T: Transport>(
+    transport: &T,
+    video_source_token: &str,
+) -> Result<ImagingOptions20, Error> {
+    Ok(imaging::get_options(
+        transport,
+        &imaging::GetOptions {
+            video_source_token: ReferenceToken(video_source_token.to_string()),
+        },
+    )
+    .await
+    .map_err(imaging_error)?
+    .imaging_options)
+}
+
+// Rejected settings are reported with `ter:InvalidArgVal` (`ter:SettingsInvalid` in a nested
+// subcode), and video sources without imaging with `ter:ActionNotSupported`.
+fn imaging_error(e: transport::Error) -> Error {
+    if is_invalid_argument(&e) {
+        Error::InvalidSettings(e.to_string())
+    } else if is_not_supported(&e) {
+        Error::Unsupported(e.to_string())
+    } else {
+        Error::Transport(e)
+    }
+}
+
+#[tokio::test]
+async fn set_settings() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<timg:SetImagingSettingsResponse xmlns:timg="http://www.onvif.org/ver20/imaging/wsdl"/>"#,
+    );
+
+    set_imaging_settings(
+        &transport,
+        "source_1",
+        ImagingSettings20 {
+            brightness: Some(60.0),
+            ..Default::default()
+        },
+        true,
+    )
+    .await
+    .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "SetImagingSettings");
+    assert_eq!(
+        request
+            .get_child("ForcePersistence")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "true"
+    );
+
+    let settings = request.get_child("ImagingSettings").unwrap();
+    assert_eq!(
+        settings
+            .get_child("Brightness")
+            .unwrap()
+            .get_text()
+            .unwrap()
+            .parse::<f64>()
+            .unwrap(),
+        60.0
+    );
+    assert!(settings.get_child("Contrast").is_none());
+
+    let transport = RecordingTransport::fault("env:Sender/ter:InvalidArgVal");
+    assert!(matches!(
+        set_imaging_settings(&transport, "source_1", Default::default(), false).await,
+        Err(Error::InvalidSettings(_))
+    ));
+
+    let transport = RecordingTransport::fault("env:Receiver/ter:ActionNotSupported");
+    assert!(matches!(
+        get_options(&transport, "source_1").await,
+        Err(Error::Unsupported(_))
+    ));
+}
@@ -2,7 +2,16 @@
 #[macro_use]
 extern crate yaserde_derive;
 
+pub mod analytics;
+pub mod camera;
+pub mod capabilities;
+pub mod devicemgmt;
 pub mod discovery;
+pub mod event;
+pub mod imaging;
+pub mod media;
+pub mod media2;
+pub mod ptz;
 pub mod soap;
 
 mod utils;
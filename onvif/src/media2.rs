@@ -0,0 +1,212 @@
+use crate::media::request_per_profile;
+use schema::{
+    media2::{self, MediaProfile},
+    onvif::ReferenceToken,
+    transport::{self, Transport},
+};
+
+/// Streaming protocol of a Media2 stream URI (`tr2:TransportProtocol`).
+///
+/// Unlike media1, which takes a `StreamSetup`, Media2 requests the URI of a stream by the name of
+/// its protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamProtocol {
+    /// RTSP streaming RTP over UDP unicast.
+    RtspUnicast,
+    /// RTSP streaming RTP over UDP multicast.
+    RtspMulticast,
+    /// RTSP streaming RTP interleaved in the RTSP connection.
+    Rtsp,
+    /// RTSP tunneled over HTTP.
+    RtspOverHttp,
+}
+
+impl StreamProtocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RtspUnicast => "RtspUnicast",
+            Self::RtspMulticast => "RtspMulticast",
+            Self::Rtsp => "RTSP",
+            Self::RtspOverHttp => "RtspOverHttp",
+        }
+    }
+}
+
+/// Lists the media profiles of the device along with all their configurations.
+pub async fn get_profiles<T: Transport>(
+    transport: &T,
+) -> Result<Vec<MediaProfile>, transport::Error> {
+    Ok(media2::get_profiles(
+        transport,
+        &media2::GetProfiles {
+            token: None,
+            // Without types, devices return the profiles without their configurations.
+            _type: vec!["All".to_string()],
+        },
+    )
+    .await?
+    .profiles)
+}
+
+/// Requests the URI of the profile's stream with the given protocol.
+pub async fn get_stream_uri<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    protocol: StreamProtocol,
+) -> Result<String, transport::Error> {
+    Ok(media2::get_stream_uri(
+        transport,
+        &media2::GetStreamUri {
+            protocol: protocol.as_str().to_string(),
+            profile_token: ReferenceToken(profile_token.to_string()),
+        },
+    )
+    .await?
+    .uri)
+}
+
+/// Requests the stream URI of each profile with the same protocol.
+///
+/// As with [`media::get_stream_uris_for_profiles`](crate::media::get_stream_uris_for_profiles),
+/// the requests are sent concurrently, up to
+/// [`MAX_CONCURRENT_STREAM_URI_REQUESTS`](crate::media::MAX_CONCURRENT_STREAM_URI_REQUESTS) at
+/// once. Returns a URI (or the error the device responded with) for each profile, keyed by the
+/// profile token, in the order of `profiles`.
+pub async fn get_stream_uris_for_profiles<T: Transport>(
+    transport: &T,
+    profiles: &[MediaProfile],
+    protocol: StreamProtocol,
+) -> Vec<(String, Result<String, transport::Error>)> {
+    request_per_profile(
+        profiles.iter().map(|profile| profile.token.0.as_str()),
+        |profile_token| get_stream_uri(transport, profile_token, protocol),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn stream_uri_request() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tr2:GetStreamUriResponse xmlns:tr2="http://www.onvif.org/ver20/media/wsdl">
+            <tr2:Uri>rtsp://192.168.0.2:554/stream1</tr2:Uri>
+        </tr2:GetStreamUriResponse>
+        "#,
+    );
+
+    assert_eq!(
+        get_stream_uri(&transport, "profile_1", StreamProtocol::RtspUnicast)
+            .await
+            .unwrap(),
+        "rtsp://192.168.0.2:554/stream1"
+    );
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "GetStreamUri");
+    assert_eq!(
+        request.get_child("Protocol").unwrap().get_text().unwrap(),
+        "RtspUnicast"
+    );
+    assert_eq!(
+        request
+            .get_child("ProfileToken")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "profile_1"
+    );
+}
+
+#[tokio::test]
+async fn media_profiles() {
+    use crate::{media::VideoCodec, utils::recording_transport::RecordingTransport};
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tr2:GetProfilesResponse
+                xmlns:tr2="http://www.onvif.org/ver20/media/wsdl"
+                xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tr2:Profiles token="profile_1" fixed="true">
+                <tr2:Name>mainStream</tr2:Name>
+                <tr2:Configurations>
+                    <tr2:VideoEncoder token="encoder_1" GovLength="50" Profile="Main">
+                        <tt:Name>encoder_1</tt:Name>
+                        <tt:UseCount>1</tt:UseCount>
+                        <tt:Encoding>H265</tt:Encoding>
+                        <tt:Resolution>
+                            <tt:Width>3840</tt:Width>
+                            <tt:Height>2160</tt:Height>
+                        </tt:Resolution>
+                        <tt:Quality>5</tt:Quality>
+                    </tr2:VideoEncoder>
+                </tr2:Configurations>
+            </tr2:Profiles>
+        </tr2:GetProfilesResponse>
+        "#,
+    );
+
+    let profiles = get_profiles(&transport).await.unwrap();
+
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].token.0, "profile_1");
+    assert_eq!(profiles[0].name.0, "mainStream");
+    assert_eq!(
+        VideoCodec::of_media_profile(&profiles[0]),
+        Some(VideoCodec::H265)
+    );
+
+    let request = transport.last_request();
+    assert_eq!(
+        request.get_child("Type").unwrap().get_text().unwrap(),
+        "All"
+    );
+}
+
+#[tokio::test]
+async fn stream_uris_for_profiles() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let profiles = ["profile_1", "profile_2"]
+        .iter()
+        .map(|token| MediaProfile {
+            token: ReferenceToken(token.to_string()),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let transport = RecordingTransport::new(
+        r#"
+        <tr2:GetStreamUriResponse xmlns:tr2="http://www.onvif.org/ver20/media/wsdl">
+            <tr2:Uri>rtsp://192.168.0.2:554/stream1</tr2:Uri>
+        </tr2:GetStreamUriResponse>
+        "#,
+    );
+    let uris = get_stream_uris_for_profiles(&transport, &profiles, StreamProtocol::Rtsp).await;
+
+    assert_eq!(
+        uris.iter()
+            .map(|(token, uri)| (token.as_str(), uri.as_deref().unwrap()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("profile_1", "rtsp://192.168.0.2:554/stream1"),
+            ("profile_2", "rtsp://192.168.0.2:554/stream1"),
+        ]
+    );
+    assert_eq!(
+        transport
+            .last_request()
+            .get_child("Protocol")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "RTSP"
+    );
+
+    let transport = RecordingTransport::fault("env:Sender/ter:InvalidArgVal");
+    let uris = get_stream_uris_for_profiles(&transport, &profiles, StreamProtocol::Rtsp).await;
+
+    assert_eq!(uris.len(), 2);
+    assert!(uris.iter().all(|(_, uri)| uri.is_err()));
+}
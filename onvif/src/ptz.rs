@@ -0,0 +1,593 @@
+use crate::utils::fault::{is_action_failed, is_invalid_argument, is_not_supported};
+use schema::{
+    onvif::{
+        AuxiliaryData, Ptzconfiguration, PtzconfigurationOptions, Ptznode, Ptzspeed, Ptzvector,
+        ReferenceToken, Vector1D, Vector2D,
+    },
+    ptz,
+    transport::{self, Transport},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The device rejected the requested position, translation or speed. The valid ranges are
+    /// reported by [`get_configuration_options`].
+    #[error("Out of range: {0}")]
+    OutOfRange(String),
+
+    /// The node has no home position, or doesn't support one (see [`Ptznode::home_supported`]).
+    /// [`set_home_position`] also fails with it if the home position is fixed.
+    #[error("No home position: {0}")]
+    NoHomePosition(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] transport::Error),
+}
+
+/// PTZ configuration along with the auxiliary commands of its PTZ node.
+#[derive(Debug)]
+pub struct Configuration {
+    pub configuration: Ptzconfiguration,
+    /// Auxiliary commands the node accepts, like `tt:Wiper|On` or `tt:IRLamp|Auto`.
+    pub auxiliary_commands: Vec<String>,
+}
+
+/// Lists the PTZ nodes of the device along with their supported spaces and capabilities.
+pub async fn get_nodes<T: Transport>(transport: &T) -> Result<Vec<Ptznode>, transport::Error> {
+    Ok(ptz::get_nodes(transport, &Default::default())
+        .await?
+        .ptz_node)
+}
+
+/// Fetches the PTZ node with the given token.
+///
+/// [`Ptznode::home_supported`] tells whether the home position operations are available, and
+/// [`Ptznode::supported_ptz_spaces`] lists the coordinate spaces the node can move in.
+pub async fn get_node<T: Transport>(
+    transport: &T,
+    node_token: &str,
+) -> Result<Ptznode, transport::Error> {
+    Ok(ptz::get_node(
+        transport,
+        &ptz::GetNode {
+            node_token: ReferenceToken(node_token.to_string()),
+        },
+    )
+    .await?
+    .ptz_node)
+}
+
+/// Fetches the PTZ configuration and the auxiliary commands supported by its node.
+pub async fn get_configuration<T: Transport>(
+    transport: &T,
+    configuration_token: &str,
+) -> Result<Configuration, transport::Error> {
+    let configuration = ptz::get_configuration(
+        transport,
+        &ptz::GetConfiguration {
+            ptz_configuration_token: ReferenceToken(configuration_token.to_string()),
+        },
+    )
+    .await?
+    .ptz_configuration;
+
+    let node = get_node(transport, &configuration.node_token.0).await?;
+
+    Ok(Configuration {
+        configuration,
+        auxiliary_commands: node
+            .auxiliary_commands
+            .into_iter()
+            .map(|command| command.0)
+            .collect(),
+    })
+}
+
+/// Sends an auxiliary command (e.g. `tt:Wiper|On`) and returns the device's response.
+///
+/// Supported commands are listed in [`Configuration::auxiliary_commands`].
+pub async fn send_auxiliary_command<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    command: &str,
+) -> Result<String, transport::Error> {
+    Ok(ptz::send_auxiliary_command(
+        transport,
+        &ptz::SendAuxiliaryCommand {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            auxiliary_data: AuxiliaryData(command.to_string()),
+        },
+    )
+    .await?
+    .auxiliary_response
+    .0)
+}
+
+/// Moves the PTZ unit to its home position.
+///
+/// `speed` can only be set if the node has speed spaces. Nodes without a home position (see
+/// [`Ptznode::home_supported`]) fail with [`Error::NoHomePosition`].
+pub async fn goto_home_position<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    speed: Option<Ptzspeed>,
+) -> Result<(), Error> {
+    ptz::goto_home_position(
+        transport,
+        &ptz::GotoHomePosition {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            speed,
+        },
+    )
+    .await
+    .map_err(home_error)?;
+
+    Ok(())
+}
+
+/// Makes the current position of the PTZ unit its home position.
+///
+/// Fails with [`Error::NoHomePosition`] if the node has no home position or if it's fixed (see
+/// [`Ptznode::fixed_home_position`]).
+pub async fn set_home_position<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+) -> Result<(), Error> {
+    ptz::set_home_position(
+        transport,
+        &ptz::SetHomePosition {
+            profile_token: ReferenceToken(profile_token.to_string()),
+        },
+    )
+    .await
+    .map_err(home_error)?;
+
+    Ok(())
+}
+
+/// Starts zooming with the given speed (negative to zoom out), without touching pan and tilt.
+///
+/// Only the `Zoom` component of the velocity is sent: some devices recenter the pan/tilt axes if
+/// they're sent as zeros.
+pub async fn continuous_zoom<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    speed: f64,
+) -> Result<(), transport::Error> {
+    ptz::continuous_move(
+        transport,
+        &ptz::ContinuousMove {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            velocity: Ptzspeed {
+                pan_tilt: None,
+                zoom: Some(Vector1D {
+                    x: speed,
+                    space: None,
+                }),
+            },
+            timeout: None,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Zooms to the given position, without touching pan and tilt.
+///
+/// Like in [`continuous_zoom`], only the `Zoom` component of the position is sent.
+pub async fn absolute_zoom<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    position: f64,
+) -> Result<(), transport::Error> {
+    ptz::absolute_move(
+        transport,
+        &ptz::AbsoluteMove {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            position: Ptzvector {
+                pan_tilt: None,
+                zoom: Some(Vector1D {
+                    x: position,
+                    space: None,
+                }),
+            },
+            speed: None,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Starts moving the PTZ unit with the given velocities, e.g. while a joystick is held.
+///
+/// The velocities are in the generic velocity space, i.e. normalized to `-1.0..=1.0` (values out of
+/// it are clamped), and the movement goes on until [`stop`] is called or the default timeout of
+/// the PTZ configuration expires.
+pub async fn continuous_move<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    pan: f64,
+    tilt: f64,
+    zoom: f64,
+) -> Result<(), transport::Error> {
+    ptz::continuous_move(
+        transport,
+        &ptz::ContinuousMove {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            velocity: Ptzspeed {
+                pan_tilt: Some(Vector2D {
+                    x: normalize(pan),
+                    y: normalize(tilt),
+                    space: None,
+                }),
+                zoom: Some(Vector1D {
+                    x: normalize(zoom),
+                    space: None,
+                }),
+            },
+            timeout: None,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Stops the ongoing pan/tilt and/or zoom movements.
+pub async fn stop<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    pan_tilt: bool,
+    zoom: bool,
+) -> Result<(), transport::Error> {
+    ptz::stop(
+        transport,
+        &ptz::Stop {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            pan_tilt: Some(pan_tilt),
+            zoom: Some(zoom),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Moves the PTZ unit to the given position.
+///
+/// Coordinates are in the generic position space, whose ranges vary per device (see
+/// [`get_configuration_options`]). `speed` applies to all the axes; if `None`, the default speed
+/// of the PTZ configuration is used.
+pub async fn absolute_move<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    pan: f64,
+    tilt: f64,
+    zoom: f64,
+    speed: Option<f64>,
+) -> Result<(), Error> {
+    ptz::absolute_move(
+        transport,
+        &ptz::AbsoluteMove {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            position: vector(pan, tilt, zoom),
+            speed: speed.map(uniform_speed),
+        },
+    )
+    .await
+    .map_err(move_error)?;
+
+    Ok(())
+}
+
+/// Moves the PTZ unit by the given translation from its current position.
+///
+/// Like in [`absolute_move`], the valid ranges of the translation vary per device.
+pub async fn relative_move<T: Transport>(
+    transport: &T,
+    profile_token: &str,
+    dx: f64,
+    dy: f64,
+    dzoom: f64,
+    speed: Option<f64>,
+) -> Result<(), Error> {
+    ptz::relative_move(
+        transport,
+        &ptz::RelativeMove {
+            profile_token: ReferenceToken(profile_token.to_string()),
+            translation: vector(dx, dy, dzoom),
+            speed: speed.map(uniform_speed),
+        },
+    )
+    .await
+    .map_err(move_error)?;
+
+    Ok(())
+}
+
+/// Fetches the options of the PTZ configuration, i.e. the coordinate spaces the device supports
+/// along with their ranges, and the PTZ timeout range.
+pub async fn get_configuration_options<T: Transport>(
+    transport: &T,
+    configuration_token: &str,
+) -> Result<PtzconfigurationOptions, transport::Error> {
+    Ok(ptz::get_configuration_options(
+        transport,
+        &ptz::GetConfigurationOptions {
+            configuration_token: ReferenceToken(configuration_token.to_string()),
+        },
+    )
+    .await?
+    .ptz_configuration_options)
+}
+
+fn vector(pan: f64, tilt: f64, zoom: f64) -> Ptzvector {
+    Ptzvector {
+        pan_tilt: Some(Vector2D {
+            x: pan,
+            y: tilt,
+            space: None,
+        }),
+        zoom: Some(Vector1D {
+            x: zoom,
+            space: None,
+        }),
+    }
+}
+
+fn uniform_speed(speed: f64) -> Ptzspeed {
+    Ptzspeed {
+        pan_tilt: Some(Vector2D {
+            x: speed,
+            y: speed,
+            space: None,
+        }),
+        zoom: Some(Vector1D {
+            x: speed,
+            space: None,
+        }),
+    }
+}
+
+// Devices report positions, translations and speeds out of their spaces with
+// `ter:InvalidArgVal` (`ter:InvalidPosition`, etc, in a nested subcode).
+fn move_error(e: transport::Error) -> Error {
+    if is_invalid_argument(&e) {
+        Error::OutOfRange(e.to_string())
+    } else {
+        Error::Transport(e)
+    }
+}
+
+// Nodes without a home position fail with `ter:Action` (with `ter:NoHomePosition`, or
+// `ter:CannotOverwriteHome` for a fixed one, in a nested subcode), and the ones which don't support
+// it with `ter:ActionNotSupported`.
+fn home_error(e: transport::Error) -> Error {
+    if is_action_failed(&e) || is_not_supported(&e) {
+        Error::NoHomePosition(e.to_string())
+    } else {
+        Error::Transport(e)
+    }
+}
+
+fn normalize(velocity: f64) -> f64 {
+    velocity.clamp(-1.0, 1.0)
+}
+
+#[tokio::test]
+async fn continuous_move_and_stop() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:ContinuousMoveResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    continuous_move(&transport, "profile_1", 0.5, -2.0, 0.0)
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "ContinuousMove");
+    assert_eq!(
+        request
+            .get_child("ProfileToken")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "profile_1"
+    );
+
+    let velocity = request.get_child("Velocity").unwrap();
+    let pan_tilt = velocity.get_child("PanTilt").unwrap();
+    let coordinate = |element: &xmltree::Element, name: &str| -> f64 {
+        element.attributes[name].parse().unwrap()
+    };
+    assert_eq!(coordinate(pan_tilt, "x"), 0.5);
+    assert_eq!(coordinate(pan_tilt, "y"), -1.0);
+    assert_eq!(coordinate(velocity.get_child("Zoom").unwrap(), "x"), 0.0);
+    assert!(request.get_child("Timeout").is_none());
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:StopResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    stop(&transport, "profile_1", true, false).await.unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "Stop");
+    assert_eq!(
+        request.get_child("PanTilt").unwrap().get_text().unwrap(),
+        "true"
+    );
+    assert_eq!(
+        request.get_child("Zoom").unwrap().get_text().unwrap(),
+        "false"
+    );
+}
+
+#[tokio::test]
+async fn zoom_only() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:ContinuousMoveResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    continuous_zoom(&transport, "profile_1", -0.5)
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "ContinuousMove");
+
+    let velocity = request.get_child("Velocity").unwrap();
+    assert!(velocity.get_child("PanTilt").is_none());
+    assert_eq!(
+        velocity.get_child("Zoom").unwrap().attributes["x"]
+            .parse::<f64>()
+            .unwrap(),
+        -0.5
+    );
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:AbsoluteMoveResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    absolute_zoom(&transport, "profile_1", 0.75).await.unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "AbsoluteMove");
+
+    let position = request.get_child("Position").unwrap();
+    assert!(position.get_child("PanTilt").is_none());
+    assert_eq!(
+        position.get_child("Zoom").unwrap().attributes["x"]
+            .parse::<f64>()
+            .unwrap(),
+        0.75
+    );
+    assert!(request.get_child("Speed").is_none());
+}
+
+#[tokio::test]
+async fn absolute_and_relative_move() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:AbsoluteMoveResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    absolute_move(&transport, "profile_1", 0.25, -0.5, 1.0, Some(0.5))
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "AbsoluteMove");
+
+    let position = request.get_child("Position").unwrap();
+    let pan_tilt = position.get_child("PanTilt").unwrap();
+    assert_eq!(pan_tilt.attributes["x"].parse::<f64>().unwrap(), 0.25);
+    assert_eq!(pan_tilt.attributes["y"].parse::<f64>().unwrap(), -0.5);
+
+    let speed = request.get_child("Speed").unwrap();
+    assert_eq!(
+        speed.get_child("Zoom").unwrap().attributes["x"]
+            .parse::<f64>()
+            .unwrap(),
+        0.5
+    );
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:RelativeMoveResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    relative_move(&transport, "profile_1", 0.1, 0.0, 0.0, None)
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "RelativeMove");
+    assert!(request.get_child("Translation").is_some());
+    assert!(request.get_child("Speed").is_none());
+
+    assert!(matches!(
+        move_error(transport::Error::Fault {
+            code: "env:Sender".to_string(),
+            subcode: Some("ter:InvalidArgVal".to_string()),
+            reason: None,
+        }),
+        Error::OutOfRange(_)
+    ));
+    assert!(matches!(
+        move_error(transport::Error::Timeout("timed out".to_string())),
+        Error::Transport(_)
+    ));
+}
+
+#[tokio::test]
+async fn home_position() {
+    use crate::utils::recording_transport::RecordingTransport;
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:GotoHomePositionResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    goto_home_position(&transport, "profile_1", Some(uniform_speed(0.5)))
+        .await
+        .unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "GotoHomePosition");
+    assert_eq!(
+        request
+            .get_child("ProfileToken")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "profile_1"
+    );
+    assert!(request.get_child("Speed").is_some());
+
+    goto_home_position(&transport, "profile_1", None)
+        .await
+        .unwrap();
+    assert!(transport.last_request().get_child("Speed").is_none());
+
+    let no_home = RecordingTransport::fault("env:Receiver/ter:Action");
+    assert!(matches!(
+        goto_home_position(&no_home, "profile_1", None).await,
+        Err(Error::NoHomePosition(_))
+    ));
+
+    let transport = RecordingTransport::new(
+        r#"<tptz:SetHomePositionResponse xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"/>"#,
+    );
+
+    set_home_position(&transport, "profile_1").await.unwrap();
+
+    let request = transport.last_request();
+    assert_eq!(request.name, "SetHomePosition");
+    assert_eq!(
+        request
+            .get_child("ProfileToken")
+            .unwrap()
+            .get_text()
+            .unwrap(),
+        "profile_1"
+    );
+
+    let not_supported = RecordingTransport::fault("env:Receiver/ter:ActionNotSupported");
+    assert!(matches!(
+        set_home_position(&not_supported, "profile_1").await,
+        Err(Error::NoHomePosition(_))
+    ));
+
+    let invalid_profile = RecordingTransport::fault("env:Sender/ter:InvalidArgVal");
+    assert!(matches!(
+        set_home_position(&invalid_profile, "profile_1").await,
+        Err(Error::Transport(_))
+    ));
+}
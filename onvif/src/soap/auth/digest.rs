@@ -1,5 +1,5 @@
 use crate::soap::client::Credentials;
-use reqwest::{RequestBuilder, Response};
+use reqwest::{header::HeaderMap, RequestBuilder, Response};
 use std::fmt::{Debug, Formatter};
 use thiserror::Error;
 use url::Url;
@@ -16,46 +16,67 @@ pub enum Error {
 
 pub struct Digest {
     creds: Option<Credentials>,
-    uri: Url,
     state: State,
+    stale_renewed: bool,
 }
 
 enum State {
     Default,
-    Got401(reqwest::Response),
+    Got401(digest_auth::WwwAuthenticateHeader),
     Got401Twice,
 }
 
 impl Digest {
-    pub fn new(uri: &Url, creds: &Option<Credentials>) -> Self {
+    pub fn new(creds: &Option<Credentials>) -> Self {
         Self {
             creds: creds.clone(),
-            uri: uri.clone(),
             state: State::Default,
+            stale_renewed: false,
         }
     }
 }
 
 impl Digest {
-    pub fn set_401(&mut self, response: Response) {
-        match self.state {
-            State::Default => self.state = State::Got401(response),
-            State::Got401(_) => self.state = State::Got401Twice,
-            State::Got401Twice => {}
-        }
+    /// Takes the challenge of a `401 Unauthorized` response.
+    ///
+    /// A second 401 means the credentials are rejected, unless the device only says that the
+    /// nonce is stale, in which case the new challenge is answered (once).
+    pub fn set_401(&mut self, response: &Response) -> Result<(), Error> {
+        let challenge = parse_challenge(response.headers())?;
+
+        self.state = match self.state {
+            State::Default => State::Got401(challenge),
+            State::Got401(_) if challenge.stale && !self.stale_renewed => {
+                self.stale_renewed = true;
+                State::Got401(challenge)
+            }
+            State::Got401(_) | State::Got401Twice => State::Got401Twice,
+        };
+
+        Ok(())
     }
 
     pub fn is_failed(&self) -> bool {
         matches!(self.state, State::Got401Twice)
     }
 
-    pub fn add_headers(&self, mut request: RequestBuilder) -> Result<RequestBuilder, Error> {
-        match &self.state {
+    /// Adds the `Authorization` header for a request to `uri`.
+    ///
+    /// Each call answers the challenge with the next nonce count and a fresh cnonce.
+    pub fn add_headers(
+        &mut self,
+        mut request: RequestBuilder,
+        uri: &Url,
+    ) -> Result<RequestBuilder, Error> {
+        match &mut self.state {
             State::Default => Ok(request),
-            State::Got401(response) => {
+            State::Got401(challenge) => {
                 let creds = self.creds.as_ref().ok_or(Error::NoCredentials)?;
 
-                request = request.header("Authorization", digest_auth(response, creds, &self.uri)?);
+                request = request.header(
+                    "Authorization",
+                    digest_auth(challenge, creds, uri, digest_auth::HttpMethod::POST)?,
+                );
 
                 Ok(request)
             }
@@ -64,20 +85,53 @@ impl Digest {
     }
 }
 
-fn digest_auth(res: &reqwest::Response, creds: &Credentials, url: &Url) -> Result<String, Error> {
-    let www_authenticate = res
-        .headers()
-        .get(reqwest::header::WWW_AUTHENTICATE)
-        .ok_or_else(|| Error::Digest("No www-authenticate header".to_string()))?
-        .to_str()
-        .map_err(|e| Error::Digest(e.to_string()))?;
+/// `Authorization` header answering the Digest challenge in `headers` of a `401 Unauthorized`
+/// response to a `GET` request to `uri`.
+///
+/// Unlike [`Digest`], it keeps no state, which is enough for one-off requests like snapshots.
+pub fn get_authorization(
+    headers: &HeaderMap,
+    creds: &Credentials,
+    uri: &Url,
+) -> Result<String, Error> {
+    let mut challenge = parse_challenge(headers)?;
+
+    digest_auth(&mut challenge, creds, uri, digest_auth::HttpMethod::GET)
+}
+
+fn parse_challenge(headers: &HeaderMap) -> Result<digest_auth::WwwAuthenticateHeader, Error> {
+    // Devices may offer several schemes, each in its own header.
+    let www_authenticate = headers
+        .get_all(reqwest::header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find(|value| {
+            value
+                .trim_start()
+                .get(..6)
+                .map_or(false, |scheme| scheme.eq_ignore_ascii_case("digest"))
+        })
+        .ok_or_else(|| Error::Digest("No www-authenticate Digest header".to_string()))?;
+
+    digest_auth::parse(www_authenticate).map_err(|e| Error::Digest(e.to_string()))
+}
+
+fn digest_auth(
+    challenge: &mut digest_auth::WwwAuthenticateHeader,
+    creds: &Credentials,
+    url: &Url,
+    method: digest_auth::HttpMethod,
+) -> Result<String, Error> {
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
 
-    let mut context = digest_auth::AuthContext::new(&creds.username, &creds.password, url.path());
+    let mut context = digest_auth::AuthContext::new(&creds.username, &creds.password, path);
 
-    context.method = digest_auth::HttpMethod::POST;
+    context.method = method;
 
-    Ok(digest_auth::parse(www_authenticate)
-        .map_err(|e| Error::Digest(e.to_string()))?
+    Ok(challenge
         .respond(&context)
         .map_err(|e| Error::Digest(e.to_string()))?
         .to_string())
@@ -1,32 +1,86 @@
-#[derive(Default, Debug, Clone)]
+/// How the password is passed in a [`UsernameToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordType {
+    /// `Base64(SHA1(nonce + created + password))`.
+    Digest,
+    /// The password itself, for the devices which don't support digests.
+    Text,
+}
+
+#[derive(Debug, Clone)]
 pub struct UsernameToken {
     pub username: String,
+    /// Base64 encoded nonce.
     pub nonce: String,
-    pub digest: String,
+    /// Password digest, or the password itself for [`PasswordType::Text`].
+    pub password: String,
+    pub password_type: PasswordType,
     pub created: String,
 }
 
 impl UsernameToken {
-    pub fn new(username: &str, password: &str) -> UsernameToken {
-        let nonce = uuid::Uuid::new_v4().to_string();
-        let created = chrono::Utc::now().to_rfc3339();
-        let concat = format!("{}{}{}", nonce, created, password);
-
-        let digest = {
-            let mut hasher = sha1::Sha1::new();
-            hasher.update(concat.as_bytes());
-            hasher.digest().bytes()
+    /// Creates a token with a password digest, a fresh nonce and the current time adjusted by
+    /// `clock_offset` (device time minus local time).
+    pub fn new(username: &str, password: &str, clock_offset: chrono::Duration) -> UsernameToken {
+        Self::with_nonce(username, password, PasswordType::Digest, clock_offset)
+    }
+
+    /// Creates a token with the password in plain text.
+    pub fn new_text(
+        username: &str,
+        password: &str,
+        clock_offset: chrono::Duration,
+    ) -> UsernameToken {
+        Self::with_nonce(username, password, PasswordType::Text, clock_offset)
+    }
+
+    fn with_nonce(
+        username: &str,
+        password: &str,
+        password_type: PasswordType,
+        clock_offset: chrono::Duration,
+    ) -> UsernameToken {
+        // 16 bytes from a CSPRNG, the nonce must be unpredictable.
+        let nonce = rand::random::<[u8; 16]>();
+        let created =
+            (chrono::Utc::now() + clock_offset).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+        Self::build(username, password, password_type, &nonce, &created)
+    }
+
+    fn build(
+        username: &str,
+        password: &str,
+        password_type: PasswordType,
+        nonce: &[u8],
+        created: &str,
+    ) -> UsernameToken {
+        let password = match password_type {
+            PasswordType::Digest => {
+                let mut hasher = sha1::Sha1::new();
+                hasher.update(nonce);
+                hasher.update(created.as_bytes());
+                hasher.update(password.as_bytes());
+                base64::encode(hasher.digest().bytes())
+            }
+            PasswordType::Text => password.to_string(),
         };
 
         UsernameToken {
             username: username.to_string(),
-            nonce: base64::encode(&nonce),
-            digest: base64::encode(digest),
-            created,
+            nonce: base64::encode(nonce),
+            password,
+            password_type,
+            created: created.to_string(),
         }
     }
 
     pub fn to_xml(&self) -> String {
+        let password_type = match self.password_type {
+            PasswordType::Digest => "PasswordDigest",
+            PasswordType::Text => "PasswordText",
+        };
+
         format!(
             r##"<?xml version="1.0" encoding="UTF-8"?>
             <wsse:Security
@@ -34,16 +88,26 @@ impl UsernameToken {
                     xmlns:wsu="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd">
                 <wsse:UsernameToken>
                     <wsse:Username>{}</wsse:Username>
-                    <wsse:Password Type="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-username-token-profile-1.0#PasswordDigest">{}</wsse:Password>
+                    <wsse:Password Type="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-username-token-profile-1.0#{}">{}</wsse:Password>
                     <wsse:Nonce EncodingType="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-soap-message-security-1.0#Base64Binary">{}</wsse:Nonce>
                     <wsu:Created>{}</wsu:Created>
                 </wsse:UsernameToken>
             </wsse:Security>"##,
-            self.username, self.digest, self.nonce, self.created
+            escape(&self.username),
+            password_type,
+            escape(&self.password),
+            self.nonce,
+            self.created
         )
     }
 }
 
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 #[test]
 fn ws_username_token_example() {
     // Example from App guide (6.1.1.3 ONVIF::AuthenticatingByWS-UsernameToken)
@@ -53,20 +117,27 @@ fn ws_username_token_example() {
     let date = "2010-09-16T07:50:45Z";
     let password = "userpassword";
 
-    let mut concat = Vec::new();
+    let token = UsernameToken::build("user", password, PasswordType::Digest, &nonce, date);
 
-    concat.extend_from_slice(&nonce);
-    concat.extend_from_slice(date.as_bytes());
-    concat.extend_from_slice(password.as_bytes());
+    assert_eq!(token.password, "tuOSpGlFlIXsozq4HFNeeGeFLEI=".to_string());
+    assert_eq!(token.nonce, "LKqI6G/AikKCQrN0zqZFlg==".to_string());
+}
 
-    let digest = {
-        let mut hasher = sha1::Sha1::new();
-        hasher.update(&concat);
-        hasher.digest().bytes()
-    };
+#[test]
+fn password_text() {
+    let token = UsernameToken::new_text("user", "pass&word", chrono::Duration::zero());
+
+    assert_eq!(token.password, "pass&word");
+    assert_eq!(base64::decode(&token.nonce).unwrap().len(), 16);
+    assert!(token.created.ends_with('Z'));
+    assert!(token
+        .to_xml()
+        .contains("username-token-profile-1.0#PasswordText\">pass&amp;word</wsse:Password>"));
+}
+
+#[test]
+fn clock_offset() {
+    let token = UsernameToken::new("user", "password", -chrono::Duration::days(365 * 60));
 
-    assert_eq!(
-        base64::encode(digest),
-        "tuOSpGlFlIXsozq4HFNeeGeFLEI=".to_string()
-    )
+    assert!(token.created.starts_with("19"));
 }
@@ -1,19 +1,41 @@
 #![allow(clippy::large_enum_variant)]
 
-use crate::soap::{
-    self,
-    auth::{digest::Digest, username_token::UsernameToken},
+use crate::{
+    soap::{
+        self,
+        auth::{
+            digest::{self, Digest},
+            username_token::UsernameToken,
+        },
+        SoapVersion, XmlProlog,
+    },
+    utils::{date_time::to_chrono, redact::redact_secrets},
 };
 use async_recursion::async_recursion;
 use async_trait::async_trait;
-use schema::transport::{Error, Transport};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE},
+    StatusCode,
+};
+use schema::transport::{self as transport, Error, ResponseStream, Transport};
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fmt::{Debug, Formatter},
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use url::Url;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 macro_rules! event {
     ($lvl:expr, $self:ident, $($arg:tt)+) => {
         tracing::event!($lvl, "{}: {}", $self.config.uri, format_args!($($arg)+))
@@ -26,15 +48,25 @@ macro_rules! debug {
     }
 }
 
+/// Request timeout used unless [`ClientBuilder::timeout`] is called.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Redirections followed unless [`ClientBuilder::max_redirects`] is called.
+pub const DEFAULT_MAX_REDIRECTS: u32 = 3;
+
 #[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
     config: Config,
+    counters: Arc<Counters>,
+    /// Device time minus local time, once known.
+    clock_offset: Arc<RwLock<Option<chrono::Duration>>>,
 }
 
 #[derive(Clone)]
 pub struct ClientBuilder {
     config: Config,
+    http_client: Option<reqwest::Client>,
 }
 
 impl ClientBuilder {
@@ -45,8 +77,28 @@ impl ClientBuilder {
                 credentials: None,
                 response_patcher: None,
                 auth_type: AuthType::Any,
-                timeout: Duration::from_secs(5),
+                auth_mode: AuthMode::UsernameTokenDigest,
+                timeout: Some(DEFAULT_TIMEOUT),
+                retries: 0,
+                retry_backoff: Duration::from_millis(500),
+                namespace_prefixes: HashMap::new(),
+                soap_version: SoapVersion::V12,
+                host_overrides: HashMap::new(),
+                log_secrets: false,
+                headers: HeaderMap::new(),
+                accept_invalid_certs: false,
+                accept_invalid_hostnames: false,
+                #[cfg(feature = "tls")]
+                identity: None,
+                auto_sync_clock: false,
+                xml_prolog: XmlProlog::Declaration,
+                ws_addressing: true,
+                services_cache: None,
+                on_exchange: None,
+                in_flight_limit: None,
+                max_redirects: DEFAULT_MAX_REDIRECTS,
             },
+            http_client: None,
         }
     }
 
@@ -65,31 +117,250 @@ impl ClientBuilder {
         self
     }
 
-    pub fn timeout(mut self, timeout: Duration) -> Self {
-        self.config.timeout = timeout;
+    /// Sets how the credentials are passed in the WS-Security header, when UsernameToken auth is
+    /// used (see [`AuthType`]). Digest by default.
+    pub fn auth_mode(mut self, auth_mode: AuthMode) -> Self {
+        self.config.auth_mode = auth_mode;
+        self
+    }
+
+    /// Sets the timeout of each HTTP request, from connecting to reading the whole response.
+    ///
+    /// Expired requests fail with [`Error::Timeout`]. `None` (or a zero duration) disables the
+    /// timeout, e.g. for long polls. Defaults to [`DEFAULT_TIMEOUT`].
+    pub fn timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.timeout = timeout.into().filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Makes [`Camera::connect_with`](crate::camera::Camera::connect_with) reuse the services
+    /// a device reported for `ttl`, instead of requesting them for each camera made for the
+    /// device. Disabled by default.
+    ///
+    /// Shorthand for a [`ServicesCache`] of the client and its copies (see [`Client::with_uri`])
+    /// only, use [`ClientBuilder::services_cache`] to share one with other clients.
+    pub fn services_ttl(self, ttl: impl Into<Option<Duration>>) -> Self {
+        let ttl = ttl.into().filter(|ttl| !ttl.is_zero());
+        self.services_cache(ttl.map(ServicesCache::new))
+    }
+
+    /// Makes the client keep the services of the device in `services_cache`, which may be shared
+    /// with other clients, see [`ClientBuilder::services_ttl`].
+    pub fn services_cache(mut self, services_cache: Option<ServicesCache>) -> Self {
+        self.config.services_cache = services_cache;
+        self
+    }
+
+    /// Retries requests which failed with transient errors (connection errors and HTTP
+    /// `503 Service Unavailable` responses other than SOAP faults) up to `retries` times. Disabled
+    /// by default.
+    ///
+    /// SOAP faults and authorization errors are never retried as they wouldn't succeed. Neither
+    /// are dropped connections and timeouts, as the device may have run the request already.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, see [`ClientBuilder::retries`]. The delay doubles
+    /// with each retry, and a random jitter of up to `base` is added. Defaults to 500 ms.
+    pub fn retry_backoff(mut self, base: Duration) -> Self {
+        self.config.retry_backoff = base;
+        self
+    }
+
+    /// Overrides the prefixes used for namespaces in request envelopes.
+    ///
+    /// The map is from namespace URI to prefix, for example
+    /// `"http://www.w3.org/2003/05/soap-envelope" => "soap"`. Strict devices may reject envelopes
+    /// which use prefixes other than they expect, even though these envelopes are valid XML.
+    ///
+    /// Namespaces whose prefixes the map gives to other namespaces are renamed to `nsN`. Requests
+    /// fail with [`Error::Protocol`] if the map gives the same prefix to several namespaces, or
+    /// renames a prefix which appears in a value of the request, like `xsi:type="tt:Duration"`.
+    pub fn namespace_prefixes(mut self, namespace_prefixes: HashMap<String, String>) -> Self {
+        self.config.namespace_prefixes = namespace_prefixes;
+        self
+    }
+
+    /// Sets the SOAP version of the requests. ONVIF requires SOAP 1.2, which is the default, but
+    /// some legacy devices understand only SOAP 1.1.
+    pub fn soap_version(mut self, soap_version: SoapVersion) -> Self {
+        self.config.soap_version = soap_version;
+        self
+    }
+
+    /// Sets what precedes the envelope in request bodies. Some devices fault on every request
+    /// unless the body starts with the exact declaration (or without it, or with a BOM).
+    pub fn xml_prolog(mut self, xml_prolog: XmlProlog) -> Self {
+        self.config.xml_prolog = xml_prolog;
+        self
+    }
+
+    /// Replaces hosts of the request URLs (including redirections).
+    ///
+    /// The map is from the host advertised by the device to the host to connect to, optionally
+    /// with a port, for example `"camera.internal" => "10.0.0.15:8080"`. Useful when devices
+    /// advertise service addresses which don't resolve from where the client runs.
+    pub fn host_overrides(mut self, host_overrides: HashMap<String, String>) -> Self {
+        self.config.host_overrides = host_overrides;
+        self
+    }
+
+    /// Logs request and response bodies as is.
+    ///
+    /// By default, UsernameToken passwords and nonces, and user info of URIs are replaced with
+    /// `***` in the logged bodies. Enable it only for debugging as the logs will leak credentials.
+    pub fn log_secrets(mut self, log_secrets: bool) -> Self {
+        self.config.log_secrets = log_secrets;
+        self
+    }
+
+    /// Calls `on_exchange` with each SOAP request and its response, including the ones which
+    /// failed with a SOAP fault or an HTTP error, e.g. to capture them to files.
+    ///
+    /// As in the logs, secrets are redacted from the bodies unless
+    /// [`log_secrets`](ClientBuilder::log_secrets) is enabled.
+    pub fn on_exchange(mut self, on_exchange: Option<ExchangeCallback>) -> Self {
+        self.config.on_exchange = on_exchange;
+        self
+    }
+
+    /// Sends WS-Addressing `MessageID`, `To`, `Action` and `ReplyTo` headers in the requests,
+    /// which is the default. `To` is the URL the request is sent to.
+    ///
+    /// Strict devices reject requests without the headers, and endpoints shared by several
+    /// resources, like the subscription managers of events, use `To` to route the requests.
+    /// Disable it for devices which fault on headers they don't understand.
+    pub fn ws_addressing(mut self, ws_addressing: bool) -> Self {
+        self.config.ws_addressing = ws_addressing;
+        self
+    }
+
+    /// Limits the number of HTTP requests the client (and its clones) has outstanding at once,
+    /// further requests wait for one of them to finish. `0` is treated as `1`.
+    ///
+    /// Use [`in_flight_limit`](ClientBuilder::in_flight_limit) to share the limit between the
+    /// clients of several services or devices.
+    pub fn max_in_flight(self, max_in_flight: usize) -> Self {
+        self.in_flight_limit(Some(InFlightLimit::new(max_in_flight)))
+    }
+
+    /// Makes the client count its requests against `in_flight_limit`, which may be shared with
+    /// other clients, e.g. ones using the same [`http_client`](ClientBuilder::http_client).
+    pub fn in_flight_limit(mut self, in_flight_limit: Option<InFlightLimit>) -> Self {
+        self.config.in_flight_limit = in_flight_limit;
+        self
+    }
+
+    /// Follows up to `max_redirects` HTTP redirections of each request, e.g. from the HTTP device
+    /// service to the HTTPS one. Defaults to [`DEFAULT_MAX_REDIRECTS`].
+    ///
+    /// The request is sent again to the new location with the same credentials, which are
+    /// authorized against it anew. Set it to `0` not to send them to hosts other than the
+    /// configured one. More redirections fail with [`Error::TooManyRedirects`], which guards
+    /// against redirection loops.
+    pub fn max_redirects(mut self, max_redirects: u32) -> Self {
+        self.config.max_redirects = max_redirects;
+        self
+    }
+
+    /// Adds a header to every SOAP request, e.g. a custom `User-Agent` or `Connection: close`.
+    ///
+    /// Headers set several times are sent with all their values. They take precedence over the
+    /// ones set by the client, except `Content-Type` which is ignored as it depends on the SOAP
+    /// version.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        if name != CONTENT_TYPE {
+            self.config.headers.append(name, value);
+        }
+        self
+    }
+
+    /// Calls [`Client::sync_clock`] before the first request with a UsernameToken, so that the
+    /// tokens are accepted by devices whose clock is off (e.g. reset to 1970 for lack of RTC).
+    pub fn auto_sync_clock(mut self, auto_sync_clock: bool) -> Self {
+        self.config.auto_sync_clock = auto_sync_clock;
+        self
+    }
+
+    /// Accepts any TLS certificate, including self-signed and expired ones.
+    ///
+    /// Cameras usually come with self-signed certificates, so HTTPS requests to them fail unless
+    /// this is enabled. Note that it makes the connections vulnerable to man-in-the-middle attacks:
+    /// the credentials (and everything else) can be intercepted by anyone on the network path.
+    /// Enable it only on trusted networks.
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Accepts TLS certificates issued for other hosts, e.g. a camera reached by IP address whose
+    /// certificate is for its hostname.
+    ///
+    /// The TLS backend can't skip the hostname check alone, so this disables the certificate
+    /// verification altogether, with the same security implications as
+    /// [`ClientBuilder::danger_accept_invalid_certs`].
+    pub fn danger_accept_invalid_hostnames(mut self, accept_invalid_hostnames: bool) -> Self {
+        self.config.accept_invalid_hostnames = accept_invalid_hostnames;
+        self
+    }
+
+    /// Presents a client certificate during the TLS handshake, for devices which require mutual
+    /// TLS. It's independent from [`ClientBuilder::credentials`], which still apply.
+    ///
+    /// With the rustls backend, the identity is made with [`reqwest::Identity::from_pem`] from a
+    /// PEM file containing the private key and the certificate chain.
+    #[cfg(feature = "tls")]
+    pub fn identity(mut self, identity: reqwest::Identity) -> Self {
+        self.config.identity = Some(identity);
+        self
+    }
+
+    /// Sends the requests with the given HTTP client instead of a new one.
+    ///
+    /// Sharing one HTTP client between the clients of all the services of a device (or of many
+    /// devices) allows to reuse its connection pool and TLS sessions. The client should be built
+    /// with `redirect::Policy::none()`, as redirections are handled by the SOAP client. The TLS
+    /// settings of the HTTP client (certificate verification, identity) are used as is.
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
         self
     }
 
     pub fn build(self) -> Client {
+        let client = match self.http_client {
+            Some(http_client) => http_client,
+            None => Self::build_http_client(&self.config),
+        };
+
+        Client {
+            client,
+            config: self.config,
+            counters: Default::default(),
+            clock_offset: Default::default(),
+        }
+    }
+
+    fn build_http_client(#[allow(unused_variables)] config: &Config) -> reqwest::Client {
         #[allow(unused_mut)]
-        let mut client_builder = reqwest::Client::builder()
-            .redirect(reqwest::redirect::Policy::none())
-            .timeout(self.config.timeout);
+        let mut client_builder =
+            reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
 
         #[cfg(feature = "tls")]
         {
-            // hyper-rustls does not support IP hosts (like https://192.168.1.2) which are
-            // very common for IP cameras. So we can use only native-tls for now.
-            // https://github.com/ctz/hyper-rustls/issues/56
-            client_builder = client_builder
-                .use_rustls_tls()
-                .danger_accept_invalid_certs(true);
-        }
+            // rustls checks the hostname as part of the certificate verification, so it can only
+            // skip both.
+            client_builder = client_builder.use_rustls_tls().danger_accept_invalid_certs(
+                config.accept_invalid_certs || config.accept_invalid_hostnames,
+            );
 
-        Client {
-            client: client_builder.build().unwrap(),
-            config: self.config,
+            if let Some(identity) = &config.identity {
+                client_builder = client_builder.identity(identity.clone());
+            }
         }
+
+        client_builder.build().unwrap()
     }
 }
 
@@ -99,7 +370,26 @@ struct Config {
     credentials: Option<Credentials>,
     response_patcher: Option<ResponsePatcher>,
     auth_type: AuthType,
-    timeout: Duration,
+    auth_mode: AuthMode,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_backoff: Duration,
+    namespace_prefixes: HashMap<String, String>,
+    soap_version: SoapVersion,
+    host_overrides: HashMap<String, String>,
+    log_secrets: bool,
+    headers: HeaderMap,
+    accept_invalid_certs: bool,
+    accept_invalid_hostnames: bool,
+    #[cfg(feature = "tls")]
+    identity: Option<reqwest::Identity>,
+    auto_sync_clock: bool,
+    xml_prolog: XmlProlog,
+    ws_addressing: bool,
+    services_cache: Option<ServicesCache>,
+    on_exchange: Option<ExchangeCallback>,
+    in_flight_limit: Option<InFlightLimit>,
+    max_redirects: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -112,6 +402,23 @@ pub enum AuthType {
     UsernameToken,
 }
 
+/// WS-Security UsernameToken flavor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthMode {
+    /// Don't send the WS-Security header.
+    None,
+    /// Send `Base64(SHA1(nonce + created + password))` with a fresh nonce for each request.
+    UsernameTokenDigest,
+    /// Send the password in plain text, for the devices which don't support digests.
+    UsernameTokenText,
+}
+
+impl Default for AuthMode {
+    fn default() -> Self {
+        AuthMode::UsernameTokenDigest
+    }
+}
+
 #[derive(Clone)]
 pub struct Credentials {
     pub username: String,
@@ -126,45 +433,426 @@ impl Debug for Credentials {
 
 pub type ResponsePatcher = Arc<dyn Fn(&str) -> Result<String, String> + Send + Sync>;
 
+/// SOAP request and its response, see [`ClientBuilder::on_exchange`].
+#[derive(Debug)]
+pub struct Exchange<'a> {
+    pub uri: &'a Url,
+    /// Envelope of the request.
+    pub request: &'a [u8],
+    pub status: reqwest::StatusCode,
+    /// Body of the response, empty if it couldn't be read.
+    pub response: &'a [u8],
+}
+
+pub type ExchangeCallback = Arc<dyn Fn(&Exchange<'_>) + Send + Sync>;
+
+/// Maximum number of outstanding HTTP requests, see [`ClientBuilder::max_in_flight`].
+///
+/// The clones of a limit are the same limit: clients built with them have at most that many
+/// requests outstanding altogether.
+#[derive(Clone, Debug)]
+pub struct InFlightLimit(Arc<Semaphore>);
+
+impl InFlightLimit {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self(Arc::new(Semaphore::new(max_in_flight.max(1))))
+    }
+}
+
+/// Namespaces and addresses of the services of a device, as reported by `GetServices`.
+pub(crate) type Services = Arc<Vec<(String, String)>>;
+
+/// Key of the services of a device in a [`ServicesCache`]: the device management URI the requests
+/// are sent to and the username.
+type ServicesKey = (Url, Option<String>);
+
+/// Services of the devices in a [`ServicesCache`] along with the time they were fetched.
+type ServicesEntries = HashMap<ServicesKey, (Instant, Services)>;
+
+/// Cache of the services reported by devices, see [`ClientBuilder::services_cache`].
+///
+/// The clones of a cache are the same cache. The services are kept for the device management URI
+/// the requests are sent to (after the [host overrides](ClientBuilder::host_overrides)) and the
+/// username, as devices may report other services to other users. Expired entries are dropped.
+#[derive(Clone, Debug)]
+pub struct ServicesCache {
+    ttl: Duration,
+    entries: Arc<Mutex<ServicesEntries>>,
+}
+
+impl ServicesCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    fn get(&self, key: &ServicesKey) -> Option<Services> {
+        let mut entries = self.lock_unexpired();
+        entries.get(key).map(|(_, services)| services.clone())
+    }
+
+    fn insert(&self, key: ServicesKey, services: Services) {
+        let mut entries = self.lock_unexpired();
+        entries.insert(key, (Instant::now(), services));
+    }
+
+    fn lock_unexpired(&self) -> std::sync::MutexGuard<'_, ServicesEntries> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+        entries
+    }
+}
+
+/// Snapshot of the request counters of a [`Client`] and all its clones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of requests made through the client.
+    pub requests: u64,
+    /// Number of HTTP requests re-sent after the first attempt (auth challenges, redirections).
+    pub retries: u64,
+    /// Number of HTTP requests which timed out.
+    pub timeouts: u64,
+    /// Number of requests currently in progress.
+    pub in_flight: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    timeouts: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> Stats {
+        Stats {
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct InFlightGuard(Arc<Counters>);
+
+impl InFlightGuard {
+    fn new(counters: &Arc<Counters>) -> Self {
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(counters.clone())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Debug)]
 enum RequestAuthType {
     Digest(Digest),
     UsernameToken,
 }
 
+/// Successful response to a SOAP request: the unwrapped (and patched) response, or the body as
+/// it's received for [`Transport::request_stream`].
+enum Body {
+    Text(String),
+    Stream(ResponseStream),
+}
+
 #[async_trait]
 impl Transport for Client {
     async fn request(&self, message: &str) -> Result<String, Error> {
+        let _in_flight = InFlightGuard::new(&self.counters);
+
+        match self.send(message, false).await? {
+            Body::Text(text) => Ok(text),
+            Body::Stream(_) => unreachable!("Only streamed requests return a stream"),
+        }
+    }
+
+    /// Streams the body of successful responses, still wrapped in the SOAP envelope. Redirections,
+    /// authentication and retries are handled as for [`Transport::request`].
+    ///
+    /// The whole response is read first if a [`ClientBuilder::response_patcher`] or an
+    /// [`ClientBuilder::on_exchange`] callback is set, as they need it.
+    async fn request_stream(&self, message: &str) -> Result<ResponseStream, Error> {
+        if self.config.response_patcher.is_some() || self.config.on_exchange.is_some() {
+            return Ok(transport::buffered(self.request(message).await?));
+        }
+
+        // The request is in flight until its body is read.
+        let in_flight = InFlightGuard::new(&self.counters);
+
+        match self.send(message, true).await? {
+            Body::Stream(stream) => Ok(Box::pin(stream.map(move |chunk| {
+                let _ = &in_flight;
+                chunk
+            }))),
+            Body::Text(text) => Ok(transport::buffered(text)),
+        }
+    }
+}
+
+impl Client {
+    /// Sends the request, retrying it after transient errors (see [`ClientBuilder::retries`]).
+    async fn send(&self, message: &str, streamed: bool) -> Result<Body, Error> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+
+        let mut attempt = 0;
+        let result = loop {
+            match self.request_with_auth(message, streamed).await {
+                Err(e) if attempt < self.config.retries && is_transient(&e) => {
+                    let delay = backoff(self.config.retry_backoff, attempt);
+                    debug!(self, "Request failed: {}. Retrying in {:?} ...", e, delay);
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+
+        let stats = self.counters.snapshot();
+        tracing::trace!(
+            uri = %self.config.uri,
+            requests = stats.requests,
+            retries = stats.retries,
+            timeouts = stats.timeouts,
+            in_flight = stats.in_flight,
+            "Request finished"
+        );
+
+        result
+    }
+
+    /// Counters of the requests made by this client and its clones.
+    pub fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+
+    /// URI of the service this client sends requests to.
+    pub fn uri(&self) -> &Url {
+        &self.config.uri
+    }
+
+    /// Services of the device in the [`ClientBuilder::services_cache`], if any.
+    pub(crate) fn cached_services(&self) -> Option<Services> {
+        let cache = self.config.services_cache.as_ref()?;
+        cache.get(&self.services_key())
+    }
+
+    /// Keeps the services of the device in the [`ClientBuilder::services_cache`], if any.
+    pub(crate) fn cache_services(&self, services: Services) {
+        if let Some(cache) = &self.config.services_cache {
+            cache.insert(self.services_key(), services);
+        }
+    }
+
+    fn services_key(&self) -> ServicesKey {
+        let uri = override_host(&self.config.uri, &self.config.host_overrides)
+            .unwrap_or_else(|_| self.config.uri.clone());
+        let username = self
+            .config
+            .credentials
+            .as_ref()
+            .map(|credentials| credentials.username.clone());
+
+        (uri, username)
+    }
+
+    /// Returns a copy of this client which sends requests to another service (or endpoint, like
+    /// an event subscription) of the same device, with the same settings.
+    pub fn with_uri(&self, uri: &Url) -> Self {
+        let mut client = self.clone();
+        client.config.uri = uri.clone();
+        client
+    }
+
+    /// Returns a copy of this client which authenticates with other credentials.
+    ///
+    /// The copy shares the HTTP connection pool (and counters) with the original client, so it's
+    /// cheap to try several credentials against the same device.
+    pub fn with_credentials(&self, credentials: Option<Credentials>) -> Self {
+        let mut client = self.clone();
+        client.config.credentials = credentials;
+        client
+    }
+
+    /// Fetches the device time and adjusts the `Created` timestamp of the next UsernameTokens by
+    /// its difference with the local time, which is returned (device time minus local time).
+    ///
+    /// `GetSystemDateAndTime` is sent without credentials, so it works even when the device
+    /// rejects them because of its clock. The offset is shared with the clones of the client.
+    pub async fn sync_clock(&self) -> Result<chrono::Duration, Error> {
+        let response = schema::devicemgmt::get_system_date_and_time(
+            &self.with_credentials(None),
+            &Default::default(),
+        )
+        .await?;
+        let now = chrono::Utc::now();
+
+        let device_time = response
+            .system_date_and_time
+            .utc_date_time
+            .as_ref()
+            .and_then(to_chrono)
+            .ok_or_else(|| Error::Protocol("Device doesn't report its UTC time".to_string()))?;
+        let offset = device_time - now;
+
+        debug!(self, "Clock offset: {}", offset);
+        *self.clock_offset.write().unwrap() = Some(offset);
+
+        Ok(offset)
+    }
+
+    /// Makes a plain HTTP `GET` request, e.g. to a snapshot URI, and returns the response as is.
+    ///
+    /// No authentication is done, the request is sent only with `headers`. This allows to handle
+    /// endpoints whose HTTP auth doesn't follow the SOAP one (like the Digest challenge of snapshot
+    /// URIs), and to read the response headers. Host overrides and the timeout apply as for SOAP
+    /// requests.
+    pub async fn get_raw(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> Result<(StatusCode, HeaderMap, Bytes), Error> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        let _in_flight = InFlightGuard::new(&self.counters);
+
+        let target = override_host(url, &self.config.host_overrides).map_err(Error::Other)?;
+
+        debug!(self, "About to make raw request to {}", target);
+
+        let mut request = self.client.get(target.as_str()).headers(headers);
+
+        if let Some(timeout) = self.config.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let _permit = self.acquire_in_flight_permit().await;
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| self.map_reqwest_error(e))?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| self.map_reqwest_error(e))?;
+
+        debug!(
+            self,
+            "Raw response status: {}, {} bytes",
+            status,
+            body.len()
+        );
+
+        Ok((status, headers, body))
+    }
+
+    /// Makes an HTTP `GET` request with the credentials of the client, e.g. to download a
+    /// snapshot, and returns the response headers and body.
+    ///
+    /// The request is first sent without credentials, then with HTTP Digest or Basic auth,
+    /// whichever the `401 Unauthorized` challenge asks for. Responses other than `2xx` fail with
+    /// [`Error::Unauthorized`] (for a `401`) or [`Error::Http`].
+    pub async fn get_authenticated(&self, url: &Url) -> Result<(HeaderMap, Bytes), Error> {
+        let (mut status, mut headers, mut body) = self.get_raw(url, HeaderMap::new()).await?;
+
+        if status == StatusCode::UNAUTHORIZED {
+            let credentials =
+                self.config.credentials.as_ref().ok_or_else(|| {
+                    Error::Unauthorized(format!("{} without credentials", status))
+                })?;
+
+            let authorization = if has_auth_scheme(&headers, "basic")
+                && !has_auth_scheme(&headers, "digest")
+            {
+                format!(
+                    "Basic {}",
+                    base64::encode(format!("{}:{}", credentials.username, credentials.password))
+                )
+            } else {
+                digest::get_authorization(&headers, credentials, url)
+                    .map_err(|e| Error::Authorization(e.to_string()))?
+            };
+
+            let mut request_headers = HeaderMap::new();
+            request_headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&authorization)
+                    .map_err(|e| Error::Authorization(e.to_string()))?,
+            );
+
+            self.counters.retries.fetch_add(1, Ordering::Relaxed);
+            let response = self.get_raw(url, request_headers).await?;
+            status = response.0;
+            headers = response.1;
+            body = response.2;
+        }
+
+        if status == StatusCode::UNAUTHORIZED {
+            Err(Error::Unauthorized(status.to_string()))
+        } else if !status.is_success() {
+            Err(Error::Http(status))
+        } else {
+            Ok((headers, body))
+        }
+    }
+
+    async fn request_with_auth(&self, message: &str, streamed: bool) -> Result<Body, Error> {
         match self.config.auth_type {
             AuthType::Any => {
-                match self.request_with_digest(message).await {
+                match self.request_with_digest(message, streamed).await {
                     Ok(success) => Ok(success),
-                    Err(Error::Authorization(e)) => {
+                    Err(Error::Authorization(e)) | Err(Error::Unauthorized(e)) => {
                         debug!(self, "Failed to authorize with Digest auth: {}. Trying UsernameToken auth ...", e);
-                        self.request_with_username_token(message).await
+                        self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                        self.request_with_username_token(message, streamed).await
                     }
                     Err(e) => Err(e),
                 }
             }
-            AuthType::Digest => self.request_with_digest(message).await,
-            AuthType::UsernameToken => self.request_with_username_token(message).await,
+            AuthType::Digest => self.request_with_digest(message, streamed).await,
+            AuthType::UsernameToken => self.request_with_username_token(message, streamed).await,
         }
     }
-}
 
-impl Client {
-    async fn request_with_digest(&self, message: &str) -> Result<String, Error> {
-        let mut auth_type =
-            RequestAuthType::Digest(Digest::new(&self.config.uri, &self.config.credentials));
+    async fn request_with_digest(&self, message: &str, streamed: bool) -> Result<Body, Error> {
+        let mut auth_type = RequestAuthType::Digest(Digest::new(&self.config.credentials));
 
-        self.request_recursive(message, &self.config.uri, &mut auth_type, 0)
+        self.request_recursive(message, &self.config.uri, &mut auth_type, 0, streamed)
             .await
     }
 
-    async fn request_with_username_token(&self, message: &str) -> Result<String, Error> {
+    async fn request_with_username_token(
+        &self,
+        message: &str,
+        streamed: bool,
+    ) -> Result<Body, Error> {
+        if self.config.auto_sync_clock
+            && self.config.credentials.is_some()
+            && self.clock_offset.read().unwrap().is_none()
+        {
+            if let Err(e) = self.sync_clock().await {
+                // Don't try again on each request, the device time is likely unavailable anyway.
+                debug!(self, "Failed to sync clock: {}", e);
+                *self.clock_offset.write().unwrap() = Some(chrono::Duration::zero());
+            }
+        }
+
         let mut auth_type = RequestAuthType::UsernameToken;
 
-        self.request_recursive(message, &self.config.uri, &mut auth_type, 0)
+        self.request_recursive(message, &self.config.uri, &mut auth_type, 0, streamed)
             .await
     }
 
@@ -175,7 +863,8 @@ impl Client {
         uri: &Url,
         auth_type: &mut RequestAuthType,
         redirections: u32,
-    ) -> Result<String, Error> {
+        streamed: bool,
+    ) -> Result<Body, Error> {
         let username_token = match auth_type {
             RequestAuthType::UsernameToken => self.username_token_auth(),
             _ => None,
@@ -186,49 +875,84 @@ impl Client {
             "About to make request. auth_type={:?}, redirections={}", auth_type, redirections
         );
 
-        let soap_msg = soap::soap(message, &username_token)
-            .map_err(|e| Error::Protocol(format!("{:?}", e)))?;
+        let to = if self.config.ws_addressing {
+            Some(uri.as_str())
+        } else {
+            None
+        };
+
+        let soap_msg = soap::soap(
+            message,
+            &username_token,
+            to,
+            &self.config.namespace_prefixes,
+            self.config.soap_version,
+            self.config.xml_prolog,
+        )
+        .map_err(|e| Error::Protocol(format!("{:?}", e)))?;
+
+        let target = override_host(uri, &self.config.host_overrides).map_err(Error::Other)?;
 
         let mut request = self
             .client
-            .post(uri.as_str())
-            .header("Content-Type", "application/soap+xml; charset=utf-8;");
+            .post(target.as_str())
+            .header("Content-Type", self.config.soap_version.content_type());
+
+        if self.config.soap_version == SoapVersion::V11 {
+            // Mandatory in SOAP 1.1 over HTTP, the SOAP stacks of some devices route requests on
+            // it. An empty value means the intent is in the body.
+            let action = soap::request_action(message).unwrap_or_default();
+            request = request.header("SOAPAction", format!("\"{}\"", action));
+        }
+
+        request = request.headers(self.config.headers.clone());
+
+        // Set per request rather than on the HTTP client, which may be shared.
+        if let Some(timeout) = self.config.timeout {
+            request = request.timeout(timeout);
+        }
 
         if let RequestAuthType::Digest(digest) = auth_type {
             request = digest
-                .add_headers(request)
+                .add_headers(request, uri)
                 .map_err(|e| Error::Authorization(e.to_string()))?;
 
             debug!(self, "Digest headers added");
         }
 
-        debug!(self, "Request body: {}", soap_msg);
+        debug!(self, "Request body: {}", self.loggable(&soap_msg));
 
-        let response = request.body(soap_msg).send().await.map_err(|e| match e {
-            e if e.is_connect() => Error::Connection(e.to_string()),
-            e if e.is_timeout() => Error::Timeout(e.to_string()),
-            e if e.is_redirect() => Error::Redirection(e.to_string()),
-            e if e.is_decode() || e.is_body() => Error::Protocol(e.to_string()),
-            e => Error::Other(e.to_string()),
-        })?;
+        let permit = self.acquire_in_flight_permit().await;
+
+        let response = request
+            .body(soap_msg.clone())
+            .send()
+            .await
+            .map_err(|e| self.map_reqwest_error(e))?;
 
         let status = response.status();
 
         debug!(self, "Response status: {}", status);
 
         if status.is_success() {
-            response
-                .text()
+            if streamed {
+                return Ok(Body::Stream(self.body_stream(response, permit)));
+            }
+
+            self.read_body(response, uri, &soap_msg)
                 .await
-                .map_err(|e| Error::Protocol(e.to_string()))
+                .map_err(|e| self.map_reqwest_error(e))
                 .and_then(|text| {
-                    debug!(self, "Response body: {}", text);
-                    let response =
-                        soap::unsoap(&text).map_err(|e| Error::Protocol(format!("{:?}", e)))?;
+                    debug!(self, "Response body: {}", self.loggable(&text));
+                    let response = soap::unsoap(&text).map_err(|e| unsoap_error(e, &text))?;
                     if let Some(response_patcher) = &self.config.response_patcher {
                         match response_patcher(&response) {
                             Ok(patched) => {
-                                debug!(self, "Response (SOAP unwrapped, patched): {}", patched);
+                                debug!(
+                                    self,
+                                    "Response (SOAP unwrapped, patched): {}",
+                                    self.loggable(&patched)
+                                );
                                 Ok(patched)
                             }
                             Err(e) => Err(Error::Protocol(format!("Patching failed: {}", e))),
@@ -237,62 +961,860 @@ impl Client {
                         Ok(response)
                     }
                 })
+                .map(Body::Text)
         } else if status == reqwest::StatusCode::UNAUTHORIZED {
             match auth_type {
                 RequestAuthType::Digest(digest) if !digest.is_failed() => {
-                    digest.set_401(response);
+                    let challenge = digest.set_401(&response);
+                    let _ = self.read_body(response, uri, &soap_msg).await;
+                    drop(permit);
+                    challenge.map_err(|e| Error::Authorization(e.to_string()))?;
+
+                    if digest.is_failed() {
+                        return Err(Error::Unauthorized(status.to_string()));
+                    }
                 }
                 _ => {
-                    if let Ok(text) = response.text().await {
-                        debug!(self, "Got Unauthorized with body: {}", text);
+                    if let Ok(text) = self.read_body(response, uri, &soap_msg).await {
+                        debug!(self, "Got Unauthorized with body: {}", self.loggable(&text));
                     }
 
-                    return Err(Error::Authorization("Unauthorized".to_string()));
+                    return Err(Error::Unauthorized(status.to_string()));
                 }
             }
 
-            self.request_recursive(message, uri, auth_type, redirections)
+            self.counters.retries.fetch_add(1, Ordering::Relaxed);
+            self.request_recursive(message, uri, auth_type, redirections, streamed)
                 .await
         } else if status.is_redirection() {
             // reqwest changes method on 302, so we have to handle redirections ourselves
             // https://github.com/seanmonstar/reqwest/issues/912
 
-            if redirections > 0 {
-                return Err(Error::Redirection("Redirection limit exceeded".to_string()));
-            }
+            let location = Client::get_redirect_location(&response, uri);
+            let _ = self.read_body(response, uri, &soap_msg).await;
+            drop(permit);
+
+            let new_url = location?;
 
-            let new_url = Client::get_redirect_location(&response)?;
+            if redirections >= self.config.max_redirects {
+                return Err(Error::TooManyRedirects(format!(
+                    "Redirected more than {} times, last to {}",
+                    self.config.max_redirects, new_url
+                )));
+            }
 
             debug!(self, "Redirecting to {} ...", new_url);
+            self.counters.retries.fetch_add(1, Ordering::Relaxed);
 
-            self.request_recursive(message, &new_url, auth_type, redirections + 1)
+            // The challenge was for the previous location, the new one has to send its own.
+            if let RequestAuthType::Digest(digest) = auth_type {
+                *digest = Digest::new(&self.config.credentials);
+            }
+
+            self.request_recursive(message, &new_url, auth_type, redirections + 1, streamed)
                 .await
         } else {
-            if let Ok(text) = response.text().await {
-                debug!(self, "Got HTTP error with body: {}", text);
-                if let Err(soap::Error::Fault(f)) = soap::unsoap(&text) {
-                    if f.is_unauthorized() {
-                        return Err(Error::Authorization("Unauthorized".to_string()));
-                    }
+            if let Ok(text) = self.read_body(response, uri, &soap_msg).await {
+                debug!(self, "Got HTTP error with body: {}", self.loggable(&text));
+                if let Err(e @ soap::Error::Fault(_)) = soap::unsoap(&text) {
+                    return Err(unsoap_error(e, &text));
                 }
             }
 
-            Err(Error::Other(status.to_string()))
+            Err(Error::Http(status))
         }
     }
 
-    fn get_redirect_location(response: &reqwest::Response) -> Result<Url, Error> {
-        response.headers()[reqwest::header::LOCATION]
+    /// Waits until the request fits in the [`ClientBuilder::max_in_flight`] limit, if any. The
+    /// permit must be dropped before sending another request from the same task.
+    async fn acquire_in_flight_permit(&self) -> Option<OwnedSemaphorePermit> {
+        let limit = self.config.in_flight_limit.as_ref()?;
+        // The semaphore is never closed.
+        limit.0.clone().acquire_owned().await.ok()
+    }
+
+    /// Body of a successful response, as it's received. The in-flight permit is held until the
+    /// body is read or the stream is dropped.
+    fn body_stream(
+        &self,
+        response: reqwest::Response,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> ResponseStream {
+        let counters = self.counters.clone();
+
+        Box::pin(futures_util::stream::try_unfold(
+            (response, permit, counters),
+            |(mut response, permit, counters)| async move {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => Ok(Some((chunk, (response, permit, counters)))),
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(reqwest_error(&counters, e)),
+                }
+            },
+        ))
+    }
+
+    /// Reads the response body and passes the exchange to the [`ClientBuilder::on_exchange`]
+    /// callback. The callback gets an empty body if it can't be read.
+    ///
+    /// ONVIF requires UTF-8, so the `charset` of the `Content-Type` is ignored. Some devices
+    /// declare a different one or send invalid bytes, those are decoded lossily.
+    async fn read_body(
+        &self,
+        response: reqwest::Response,
+        uri: &Url,
+        request: &str,
+    ) -> Result<String, reqwest::Error> {
+        let status = response.status();
+        let body = response.bytes().await.map(|bytes| {
+            String::from_utf8(bytes.to_vec()).unwrap_or_else(|e| {
+                debug!(self, "Response body isn't valid UTF-8: {}", e);
+                String::from_utf8_lossy(e.as_bytes()).into_owned()
+            })
+        });
+
+        if let Some(on_exchange) = &self.config.on_exchange {
+            let request = self.loggable(request);
+            let response = self.loggable(body.as_deref().unwrap_or_default());
+
+            on_exchange(&Exchange {
+                uri,
+                request: request.as_bytes(),
+                status,
+                response: response.as_bytes(),
+            });
+        }
+
+        body
+    }
+
+    fn map_reqwest_error(&self, e: reqwest::Error) -> Error {
+        reqwest_error(&self.counters, e)
+    }
+
+    /// Location a response redirects to, which may be relative to `uri`.
+    fn get_redirect_location(response: &reqwest::Response, uri: &Url) -> Result<Url, Error> {
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .ok_or_else(|| Error::Redirection("Missing Location header".to_string()))?
             .to_str()
-            .map_err(|e| Error::Redirection(e.to_string()))?
-            .parse::<Url>()
+            .map_err(|e| Error::Redirection(e.to_string()))?;
+
+        uri.join(location)
             .map_err(|e| Error::Redirection(e.to_string()))
     }
 
+    /// Body as it may be logged, see [`ClientBuilder::log_secrets`].
+    fn loggable<'a>(&self, body: &'a str) -> Cow<'a, str> {
+        if self.config.log_secrets {
+            Cow::Borrowed(body)
+        } else {
+            Cow::Owned(redact_secrets(body))
+        }
+    }
+
     pub fn username_token_auth(&self) -> Option<UsernameToken> {
-        self.config
-            .credentials
-            .as_ref()
-            .map(|c| UsernameToken::new(&c.username, &c.password))
+        let c = self.config.credentials.as_ref()?;
+        let offset = self
+            .clock_offset
+            .read()
+            .unwrap()
+            .unwrap_or_else(chrono::Duration::zero);
+
+        match self.config.auth_mode {
+            AuthMode::None => None,
+            AuthMode::UsernameTokenDigest => {
+                Some(UsernameToken::new(&c.username, &c.password, offset))
+            }
+            AuthMode::UsernameTokenText => {
+                Some(UsernameToken::new_text(&c.username, &c.password, offset))
+            }
+        }
+    }
+}
+
+/// Error reported for a response `text` which [`soap::unsoap`] rejected, whatever the HTTP
+/// status of the response.
+///
+/// Faults are reported as [`Error::Fault`], or as [`Error::Unauthorized`] for the
+/// `ter:NotAuthorized` ones so that rejected credentials can be told from other failures.
+fn unsoap_error(e: soap::Error, text: &str) -> Error {
+    match e {
+        soap::Error::Fault(fault) => {
+            let error = Error::Fault {
+                code: fault.code.value.0.clone(),
+                subcode: fault
+                    .code
+                    .subcode
+                    .as_ref()
+                    .map(|subcode| subcode.value.clone()),
+                reason: fault
+                    .reason
+                    .text
+                    .iter()
+                    .map(|reason| reason.text.trim())
+                    .find(|reason| !reason.is_empty())
+                    .map(str::to_string),
+            };
+
+            if fault.is_unauthorized() {
+                Error::Unauthorized(error.to_string())
+            } else {
+                error
+            }
+        }
+        e @ soap::Error::InternalError(_) => Error::Protocol(format!("{:?}", e)),
+        e => Error::deserialization(text, format!("{:?}", e)),
+    }
+}
+
+/// Error reported for a `fault` element found while reading a streamed response, in an envelope
+/// of the `envelope_namespace`.
+pub(crate) fn fault_error(fault: &str, envelope_namespace: &str) -> Error {
+    let text = format!(
+        r#"<s:Envelope xmlns:s="{}"><s:Body>{}</s:Body></s:Envelope>"#,
+        envelope_namespace, fault
+    );
+
+    match soap::unsoap(&text) {
+        Err(e) => unsoap_error(e, &text),
+        Ok(_) => Error::deserialization(&text, "Fault expected"),
+    }
+}
+
+/// Whether one of the `WWW-Authenticate` headers offers the auth `scheme`, e.g. `digest`.
+fn has_auth_scheme(headers: &HeaderMap, scheme: &str) -> bool {
+    headers
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| {
+            value
+                .trim_start()
+                .get(..scheme.len())
+                .map_or(false, |prefix| prefix.eq_ignore_ascii_case(scheme))
+        })
+}
+
+/// Whether the connection was closed or reset after it was established, e.g. by a device
+/// restarting while it responds.
+fn is_dropped_connection(e: &reqwest::Error) -> bool {
+    use std::{error::Error as _, io::ErrorKind};
+
+    let mut source = e.source();
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<hyper::Error>() {
+            if e.is_incomplete_message() || e.is_closed() {
+                return true;
+            }
+        }
+        if let Some(e) = e.downcast_ref::<std::io::Error>() {
+            if matches!(
+                e.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        source = e.source();
+    }
+
+    false
+}
+
+/// Whether a request which failed with `e` may succeed if sent again, and is safe to resend.
+///
+/// Only the failures which mean that the device didn't get or didn't run the request qualify.
+/// After a dropped connection or a timeout it may have run it already, and resending
+/// `SystemReboot` or `RelativeMove` would run it twice.
+fn reqwest_error(counters: &Counters, e: reqwest::Error) -> Error {
+    match e {
+        e if e.is_connect() => Error::Connection(e.to_string()),
+        e if e.is_timeout() => {
+            counters.timeouts.fetch_add(1, Ordering::Relaxed);
+            Error::Timeout(e.to_string())
+        }
+        e if e.is_redirect() => Error::Redirection(e.to_string()),
+        e if is_dropped_connection(&e) => Error::ConnectionDropped(e.to_string()),
+        e if e.is_decode() || e.is_body() => Error::Protocol(e.to_string()),
+        e => Error::Other(e.to_string()),
+    }
+}
+
+fn is_transient(e: &Error) -> bool {
+    match e {
+        Error::Connection(_) => true,
+        Error::Http(status) => *status == StatusCode::SERVICE_UNAVAILABLE,
+        _ => false,
+    }
+}
+
+/// Delay before the retry number `attempt` (counted from 0).
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    // Random fraction in [0, 1).
+    let jitter = rand::random::<f64>();
+
+    base.saturating_mul(1 << attempt.min(16)) + base.mul_f64(jitter)
+}
+
+fn override_host(uri: &Url, host_overrides: &HashMap<String, String>) -> Result<Url, String> {
+    let host_override = match uri.host_str().and_then(|host| host_overrides.get(host)) {
+        Some(host_override) => host_override,
+        None => return Ok(uri.clone()),
+    };
+
+    // Parse the override as an authority to handle the port and IPv6 addresses properly. The
+    // scheme has no default port, so an explicit `:80` is not lost.
+    let authority = Url::parse(&format!("authority://{}", host_override))
+        .map_err(|e| format!("Invalid host override {}: {}", host_override, e))?;
+
+    let mut uri = uri.clone();
+    uri.set_host(authority.host_str())
+        .map_err(|e| format!("Invalid host override {}: {}", host_override, e))?;
+    if let Some(port) = authority.port() {
+        uri.set_port(Some(port))
+            .map_err(|_| format!("Invalid host override {}", host_override))?;
+    }
+
+    Ok(uri)
+}
+
+#[test]
+fn test_unsoap_error() {
+    let fault = |subcode: &str| {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:ter="http://www.onvif.org/ver10/error"><s:Body><s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>{}</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en">Fault</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#,
+            subcode
+        )
+    };
+    let error = |text: &str| unsoap_error(soap::unsoap(text).unwrap_err(), text);
+
+    assert!(matches!(
+        error(&fault("ter:NotAuthorized")),
+        Error::Unauthorized(message) if message == "SOAP fault s:Sender/ter:NotAuthorized"
+    ));
+    match error(&fault("ter:InvalidArgVal")) {
+        e @ Error::Fault { .. } => {
+            assert_eq!(e.to_string(), "SOAP fault s:Sender/ter:InvalidArgVal");
+            assert!(matches!(
+                e,
+                Error::Fault { code, subcode, reason }
+                    if code == "s:Sender"
+                        && subcode.as_deref() == Some("ter:InvalidArgVal")
+                        && reason.as_deref() == Some("Fault")
+            ));
+        }
+        e => panic!("unexpected error: {:?}", e),
     }
+    assert!(matches!(
+        error("<html>Not found</html>"),
+        Error::Deserialization { .. }
+    ));
+}
+
+#[test]
+fn test_has_auth_scheme() {
+    let mut headers = HeaderMap::new();
+    headers.append(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Basic realm="camera""#),
+    );
+    headers.append(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"digest realm="camera", nonce="abc", qop="auth""#),
+    );
+
+    assert!(has_auth_scheme(&headers, "basic"));
+    assert!(has_auth_scheme(&headers, "digest"));
+    assert!(!has_auth_scheme(&headers, "bearer"));
+    assert!(!has_auth_scheme(&HeaderMap::new(), "basic"));
+}
+
+#[test]
+fn test_retries() {
+    assert!(is_transient(&Error::Connection(
+        "tcp connect error: Connection refused (os error 111)".to_string()
+    )));
+    assert!(is_transient(&Error::Http(StatusCode::SERVICE_UNAVAILABLE)));
+    assert!(!is_transient(&Error::Timeout("timed out".to_string())));
+    assert!(!is_transient(&Error::ConnectionDropped(
+        "connection closed before message completed".to_string()
+    )));
+    assert!(!is_transient(&Error::Http(
+        StatusCode::INTERNAL_SERVER_ERROR
+    )));
+    assert!(!is_transient(&Error::Http(StatusCode::NOT_FOUND)));
+    assert!(!is_transient(&Error::Other(
+        "503 Service Unavailable".to_string()
+    )));
+    assert!(!is_transient(&Error::Fault {
+        code: "env:MustUnderstand".to_string(),
+        subcode: None,
+        reason: None,
+    }));
+    assert!(!is_transient(&Error::Authorization(
+        "Unauthorized".to_string()
+    )));
+    assert!(!is_transient(&Error::Unauthorized(
+        StatusCode::UNAUTHORIZED.to_string()
+    )));
+
+    let base = Duration::from_millis(100);
+    let delay = backoff(base, 2);
+    assert!(delay >= Duration::from_millis(400) && delay < Duration::from_millis(500));
+}
+
+#[test]
+fn test_services_cache() {
+    let uri = Url::parse("http://192.168.0.2/onvif/device_service").unwrap();
+    let services: Services = Arc::new(vec![(
+        "http://www.onvif.org/ver10/media/wsdl".to_string(),
+        "http://192.168.0.2/onvif/media_service".to_string(),
+    )]);
+    let cache = ServicesCache::new(Duration::from_secs(60));
+    let client = ClientBuilder::new(&uri)
+        .services_cache(Some(cache.clone()))
+        .build();
+
+    client.cache_services(services.clone());
+
+    assert_eq!(client.cached_services(), Some(services.clone()));
+    assert_eq!(
+        ClientBuilder::new(&uri)
+            .services_cache(Some(cache.clone()))
+            .build()
+            .cached_services(),
+        Some(services.clone())
+    );
+
+    // Other clients, users and hosts have their own entries.
+    assert_eq!(
+        ClientBuilder::new(&uri)
+            .services_ttl(Duration::from_secs(60))
+            .build()
+            .cached_services(),
+        None
+    );
+    assert_eq!(
+        client
+            .with_credentials(Some(Credentials {
+                username: "operator".to_string(),
+                password: "password".to_string(),
+            }))
+            .cached_services(),
+        None
+    );
+    assert_eq!(
+        ClientBuilder::new(&uri)
+            .services_cache(Some(cache.clone()))
+            .host_overrides(
+                vec![("192.168.0.2".to_string(), "10.0.0.2".to_string())]
+                    .into_iter()
+                    .collect()
+            )
+            .build()
+            .cached_services(),
+        None
+    );
+
+    // Expired entries are dropped.
+    let expired = ServicesCache::new(Duration::from_secs(0));
+    let client = ClientBuilder::new(&uri)
+        .services_cache(Some(expired.clone()))
+        .build();
+    client.cache_services(services);
+    assert_eq!(client.cached_services(), None);
+    assert!(expired.entries.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_override_host() {
+    let overrides = vec![
+        ("camera.internal".to_string(), "10.0.0.15:8080".to_string()),
+        ("192.168.1.2".to_string(), "gateway.example".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    let check = |uri: &str| {
+        override_host(&Url::parse(uri).unwrap(), &overrides)
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(
+        check("http://camera.internal/onvif/device_service"),
+        "http://10.0.0.15:8080/onvif/device_service"
+    );
+    assert_eq!(
+        check("http://192.168.1.2:81/onvif/media"),
+        "http://gateway.example:81/onvif/media"
+    );
+    assert_eq!(
+        check("http://10.0.0.1/onvif/media"),
+        "http://10.0.0.1/onvif/media"
+    );
+}
+
+/// Accepts a single HTTP request, answers it with `status` (e.g. `200 OK`) and `response_body`,
+/// and returns the request.
+#[cfg(test)]
+async fn serve_once(
+    status: &'static str,
+    response_body: &'static str,
+) -> (Url, tokio::task::JoinHandle<String>) {
+    serve_once_with_headers(status, String::new(), response_body).await
+}
+
+/// Like [`serve_once`], with `headers` (e.g. `Location: /onvif\r\n`) added to the response.
+#[cfg(test)]
+async fn serve_once_with_headers(
+    status: &'static str,
+    headers: String,
+    response_body: &'static str,
+) -> (Url, tokio::task::JoinHandle<String>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = Url::parse(&format!(
+        "http://{}/onvif/device_service",
+        listener.local_addr().unwrap()
+    ))
+    .unwrap();
+
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+
+            let text = String::from_utf8_lossy(&request).to_lowercase();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let content_length = text
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |length| length.trim().parse().unwrap());
+                if n == 0 || request.len() >= end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 {}\r\n{}Content-Type: application/soap+xml\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    headers,
+                    response_body.len(),
+                    response_body
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        String::from_utf8(request).unwrap()
+    });
+
+    (uri, handle)
+}
+
+#[tokio::test]
+async fn test_custom_headers() {
+    let (uri, server) = serve_once(
+        "200 OK",
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><Ok/></s:Body></s:Envelope>"#,
+    )
+    .await;
+
+    let client = ClientBuilder::new(&uri)
+        .header(
+            HeaderName::from_static("x-vendor-token"),
+            HeaderValue::from_static("secret"),
+        )
+        .header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+        .build();
+
+    let response = client.request("<GetDeviceInformation/>").await.unwrap();
+    let request = server.await.unwrap().to_lowercase();
+
+    assert!(response.contains("Ok"));
+    assert!(request.contains("x-vendor-token: secret\r\n"));
+    assert!(request.contains("content-type: application/soap+xml"));
+    assert!(!request.contains("text/plain"));
+}
+
+#[tokio::test]
+async fn test_on_exchange() {
+    let (uri, server) = serve_once(
+        "500 Internal Server Error",
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:ter="http://www.onvif.org/ver10/error"><s:Body><s:Fault><s:Code><s:Value>s:Receiver</s:Value><s:Subcode><s:Value>ter:ActionNotSupported</s:Value></s:Subcode></s:Code><s:Reason><s:Text xml:lang="en">Not supported</s:Text></s:Reason></s:Fault></s:Body></s:Envelope>"#,
+    )
+    .await;
+
+    let exchanges = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = exchanges.clone();
+
+    let client = ClientBuilder::new(&uri)
+        .on_exchange(Some(Arc::new(move |exchange: &Exchange<'_>| {
+            recorded.lock().unwrap().push((
+                exchange.uri.clone(),
+                String::from_utf8_lossy(exchange.request).into_owned(),
+                exchange.status,
+                String::from_utf8_lossy(exchange.response).into_owned(),
+            ));
+        })))
+        .build();
+
+    let result = client.request("<GetDeviceInformation/>").await;
+    server.await.unwrap();
+
+    assert!(matches!(result, Err(Error::Fault { .. })));
+
+    let exchanges = exchanges.lock().unwrap();
+    assert_eq!(exchanges.len(), 1);
+
+    let (exchange_uri, request, status, response) = &exchanges[0];
+    assert_eq!(exchange_uri, &uri);
+    assert!(request.contains("<GetDeviceInformation"));
+    assert_eq!(*status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.contains("ter:ActionNotSupported"));
+}
+
+#[tokio::test]
+async fn test_request_stream() {
+    const ENVELOPE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><GetDeviceInformationResponse/></s:Body></s:Envelope>"#;
+
+    let (uri, server) = serve_once("200 OK", ENVELOPE).await;
+    let client = ClientBuilder::new(&uri).build();
+
+    let mut stream = client
+        .request_stream("<GetDeviceInformation/>")
+        .await
+        .unwrap();
+    assert_eq!(client.stats().in_flight, 1);
+
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    drop(stream);
+    server.await.unwrap();
+
+    // The envelope is left to the caller.
+    assert_eq!(body, ENVELOPE.as_bytes());
+    assert_eq!(client.stats().in_flight, 0);
+
+    // The response patcher needs the whole response, unwrapped.
+    let (uri, server) = serve_once("200 OK", ENVELOPE).await;
+    let client = ClientBuilder::new(&uri)
+        .response_patcher(Some(Arc::new(|response: &str| Ok(response.to_string()))))
+        .build();
+
+    let mut stream = client
+        .request_stream("<GetDeviceInformation/>")
+        .await
+        .unwrap();
+    server.await.unwrap();
+
+    let response = stream.next().await.unwrap().unwrap();
+    assert!(String::from_utf8_lossy(&response).contains("<GetDeviceInformationResponse"));
+    assert!(!String::from_utf8_lossy(&response).contains("Envelope"));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_truncated_response() {
+    let (uri, server) = serve_once(
+        "200 OK",
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><GetDeviceInformationResponse>"#,
+    )
+    .await;
+
+    let result = ClientBuilder::new(&uri)
+        .build()
+        .request("<GetDeviceInformation/>")
+        .await;
+    server.await.unwrap();
+
+    match result {
+        Err(Error::Deserialization { body_snippet, .. }) => {
+            assert!(body_snippet.ends_with("<GetDeviceInformationResponse>"))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn test_soap_11_action() {
+    let (uri, server) = serve_once(
+        "200 OK",
+        r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body><Ok/></s:Body></s:Envelope>"#,
+    )
+    .await;
+
+    ClientBuilder::new(&uri)
+        .soap_version(SoapVersion::V11)
+        .build()
+        .request(
+            r#"<tds:GetDeviceInformation xmlns:tds="http://www.onvif.org/ver10/device/wsdl"/>"#,
+        )
+        .await
+        .unwrap();
+    let request = server.await.unwrap().to_lowercase();
+
+    assert!(request.contains(
+        "soapaction: \"http://www.onvif.org/ver10/device/wsdl/getdeviceinformation\"\r\n"
+    ));
+}
+
+#[tokio::test]
+async fn test_dropped_connection() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = Url::parse(&format!(
+        "http://{}/onvif/device_service",
+        listener.local_addr().unwrap()
+    ))
+    .unwrap();
+
+    let server = tokio::spawn(async move {
+        // Half of the announced body is sent before the connection is closed.
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = stream.read(&mut [0; 4096]).await.unwrap();
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: application/soap+xml\r\nContent-Length: 100\r\n\r\n<s:Envelope",
+            )
+            .await
+            .unwrap();
+    });
+
+    let client = ClientBuilder::new(&uri)
+        .retries(1)
+        .retry_backoff(Duration::from_millis(1))
+        .build();
+    let result = client.request("<GetDeviceInformation/>").await;
+    server.await.unwrap();
+
+    assert!(
+        matches!(result, Err(Error::ConnectionDropped(_))),
+        "unexpected result: {:?}",
+        result
+    );
+    // The device may have run the request, so it's not sent again.
+    assert_eq!(client.stats().retries, 0);
+}
+
+#[tokio::test]
+async fn test_ws_addressing() {
+    const RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><Ok/></s:Body></s:Envelope>"#;
+    const REQUEST: &str =
+        r#"<tds:GetDeviceInformation xmlns:tds="http://www.onvif.org/ver10/device/wsdl"/>"#;
+
+    let (uri, server) = serve_once("200 OK", RESPONSE).await;
+    ClientBuilder::new(&uri)
+        .build()
+        .request(REQUEST)
+        .await
+        .unwrap();
+    let request = server.await.unwrap();
+
+    assert!(request.contains(">urn:uuid:"));
+    assert!(request.contains(&format!(">{}</wsa:To>", uri)));
+    assert!(request
+        .contains(">http://www.onvif.org/ver10/device/wsdl/GetDeviceInformation</wsa:Action>"));
+    assert!(request.contains("<wsa:ReplyTo"));
+
+    let (uri, server) = serve_once("200 OK", RESPONSE).await;
+    ClientBuilder::new(&uri)
+        .ws_addressing(false)
+        .build()
+        .request(REQUEST)
+        .await
+        .unwrap();
+
+    assert!(!server.await.unwrap().contains("wsa:"));
+}
+
+#[tokio::test]
+async fn test_max_in_flight() {
+    let (uri, server) = serve_once(
+        "200 OK",
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><Ok/></s:Body></s:Envelope>"#,
+    )
+    .await;
+
+    let limit = InFlightLimit::new(1);
+    let busy = ClientBuilder::new(&uri)
+        .in_flight_limit(Some(limit.clone()))
+        .build();
+    let client = ClientBuilder::new(&uri)
+        .in_flight_limit(Some(limit))
+        .build();
+
+    let permit = busy.acquire_in_flight_permit().await;
+    assert!(permit.is_some());
+
+    let request = client.request("<GetDeviceInformation/>");
+    tokio::pin!(request);
+
+    // The other client holds the only permit.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), &mut request)
+            .await
+            .is_err()
+    );
+
+    drop(permit);
+    request.await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_redirects() {
+    let (target, target_server) = serve_once(
+        "200 OK",
+        r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body><Ok/></s:Body></s:Envelope>"#,
+    )
+    .await;
+    let (uri, server) =
+        serve_once_with_headers("302 Found", format!("Location: {}\r\n", target), "").await;
+
+    let response = ClientBuilder::new(&uri)
+        .build()
+        .request("<GetDeviceInformation/>")
+        .await
+        .unwrap();
+
+    assert!(response.contains("Ok"));
+    assert!(server.await.unwrap().starts_with("POST "));
+    let request = target_server.await.unwrap();
+    assert!(request.starts_with("POST /onvif/device_service "));
+    assert!(request.contains("<GetDeviceInformation"));
+
+    let (uri, server) = serve_once_with_headers(
+        "301 Moved Permanently",
+        format!("Location: {}\r\n", uri),
+        "",
+    )
+    .await;
+
+    let result = ClientBuilder::new(&uri)
+        .max_redirects(0)
+        .build()
+        .request("<GetDeviceInformation/>")
+        .await;
+    server.await.unwrap();
+
+    assert!(matches!(result, Err(Error::TooManyRedirects(_))));
 }
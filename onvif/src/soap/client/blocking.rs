@@ -0,0 +1,286 @@
+//! Blocking counterparts of [`Client`](super::Client) and [`ClientBuilder`](super::ClientBuilder),
+//! for callers without an async runtime (CLI tools, FFI bindings).
+//!
+//! A blocking client runs the requests of the async one on its own single-threaded runtime. It
+//! must not be used from within an async runtime, where blocking the thread panics.
+
+use super::{
+    AuthMode, AuthType, Credentials, ExchangeCallback, InFlightLimit, ResponsePatcher, Stats,
+};
+use crate::soap::{SoapVersion, XmlProlog};
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+use schema::{
+    devicemgmt, media,
+    transport::{Error, Transport},
+};
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// Builder of a blocking [`Client`]. Its methods are the ones of [`super::ClientBuilder`], the
+/// others can be called on a [`super::ClientBuilder`] turned into this one with `into()`.
+pub struct ClientBuilder {
+    inner: super::ClientBuilder,
+}
+
+impl ClientBuilder {
+    pub fn new(uri: &Url) -> Self {
+        Self {
+            inner: super::ClientBuilder::new(uri),
+        }
+    }
+
+    pub fn credentials(self, credentials: Option<Credentials>) -> Self {
+        self.map(|inner| inner.credentials(credentials))
+    }
+
+    pub fn response_patcher(self, response_patcher: Option<ResponsePatcher>) -> Self {
+        self.map(|inner| inner.response_patcher(response_patcher))
+    }
+
+    pub fn auth_type(self, auth_type: AuthType) -> Self {
+        self.map(|inner| inner.auth_type(auth_type))
+    }
+
+    pub fn auth_mode(self, auth_mode: AuthMode) -> Self {
+        self.map(|inner| inner.auth_mode(auth_mode))
+    }
+
+    pub fn timeout(self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.map(|inner| inner.timeout(timeout))
+    }
+
+    pub fn retries(self, retries: u32) -> Self {
+        self.map(|inner| inner.retries(retries))
+    }
+
+    pub fn retry_backoff(self, base: Duration) -> Self {
+        self.map(|inner| inner.retry_backoff(base))
+    }
+
+    pub fn namespace_prefixes(self, namespace_prefixes: HashMap<String, String>) -> Self {
+        self.map(|inner| inner.namespace_prefixes(namespace_prefixes))
+    }
+
+    pub fn soap_version(self, soap_version: SoapVersion) -> Self {
+        self.map(|inner| inner.soap_version(soap_version))
+    }
+
+    pub fn xml_prolog(self, xml_prolog: XmlProlog) -> Self {
+        self.map(|inner| inner.xml_prolog(xml_prolog))
+    }
+
+    pub fn host_overrides(self, host_overrides: HashMap<String, String>) -> Self {
+        self.map(|inner| inner.host_overrides(host_overrides))
+    }
+
+    pub fn log_secrets(self, log_secrets: bool) -> Self {
+        self.map(|inner| inner.log_secrets(log_secrets))
+    }
+
+    pub fn on_exchange(self, on_exchange: Option<ExchangeCallback>) -> Self {
+        self.map(|inner| inner.on_exchange(on_exchange))
+    }
+
+    pub fn ws_addressing(self, ws_addressing: bool) -> Self {
+        self.map(|inner| inner.ws_addressing(ws_addressing))
+    }
+
+    pub fn max_in_flight(self, max_in_flight: usize) -> Self {
+        self.map(|inner| inner.max_in_flight(max_in_flight))
+    }
+
+    pub fn in_flight_limit(self, in_flight_limit: Option<InFlightLimit>) -> Self {
+        self.map(|inner| inner.in_flight_limit(in_flight_limit))
+    }
+
+    pub fn max_redirects(self, max_redirects: u32) -> Self {
+        self.map(|inner| inner.max_redirects(max_redirects))
+    }
+
+    pub fn header(self, name: HeaderName, value: HeaderValue) -> Self {
+        self.map(|inner| inner.header(name, value))
+    }
+
+    pub fn auto_sync_clock(self, auto_sync_clock: bool) -> Self {
+        self.map(|inner| inner.auto_sync_clock(auto_sync_clock))
+    }
+
+    pub fn danger_accept_invalid_certs(self, accept_invalid_certs: bool) -> Self {
+        self.map(|inner| inner.danger_accept_invalid_certs(accept_invalid_certs))
+    }
+
+    pub fn danger_accept_invalid_hostnames(self, accept_invalid_hostnames: bool) -> Self {
+        self.map(|inner| inner.danger_accept_invalid_hostnames(accept_invalid_hostnames))
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn identity(self, identity: reqwest::Identity) -> Self {
+        self.map(|inner| inner.identity(identity))
+    }
+
+    /// See [`super::ClientBuilder::http_client`]. The HTTP client must be an async one, as it's
+    /// driven by the runtime of the blocking client.
+    pub fn http_client(self, http_client: reqwest::Client) -> Self {
+        self.map(|inner| inner.http_client(http_client))
+    }
+
+    /// Builds the client along with its runtime, which fails only if the runtime can't be made.
+    pub fn build(self) -> std::io::Result<Client> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Client {
+            inner: self.inner.build(),
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    fn map(self, f: impl FnOnce(super::ClientBuilder) -> super::ClientBuilder) -> Self {
+        Self {
+            inner: f(self.inner),
+        }
+    }
+}
+
+impl From<super::ClientBuilder> for ClientBuilder {
+    fn from(inner: super::ClientBuilder) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking SOAP client, see the [module](self) documentation.
+///
+/// Clones share the runtime, the HTTP connection pool and the counters.
+#[derive(Clone)]
+pub struct Client {
+    inner: super::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Sends the SOAP request `message` and returns the response, see [`Transport::request`].
+    pub fn request(&self, message: &str) -> Result<String, Error> {
+        self.block_on(self.inner.request(message))
+    }
+
+    /// Runs `future` to completion on the runtime of the client, e.g. an operation of the
+    /// schema called with [`Client::as_async`]:
+    ///
+    /// ```ignore
+    /// let info = client.block_on(schema::devicemgmt::get_device_information(
+    ///     client.as_async(),
+    ///     &Default::default(),
+    /// ))?;
+    /// ```
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Async client the requests are made with. Its futures have to be run with
+    /// [`Client::block_on`] outside of an async runtime.
+    pub fn as_async(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// See [`super::Client::stats`].
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+
+    /// See [`super::Client::uri`].
+    pub fn uri(&self) -> &Url {
+        self.inner.uri()
+    }
+
+    /// See [`super::Client::with_uri`].
+    pub fn with_uri(&self, uri: &Url) -> Self {
+        Self {
+            inner: self.inner.with_uri(uri),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// See [`super::Client::with_credentials`].
+    pub fn with_credentials(&self, credentials: Option<Credentials>) -> Self {
+        Self {
+            inner: self.inner.with_credentials(credentials),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// See [`super::Client::sync_clock`].
+    pub fn sync_clock(&self) -> Result<chrono::Duration, Error> {
+        self.block_on(self.inner.sync_clock())
+    }
+
+    /// See [`super::Client::get_raw`].
+    pub fn get_raw(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> Result<(StatusCode, HeaderMap, Bytes), Error> {
+        self.block_on(self.inner.get_raw(url, headers))
+    }
+
+    /// See [`super::Client::get_authenticated`].
+    pub fn get_authenticated(&self, url: &Url) -> Result<(HeaderMap, Bytes), Error> {
+        self.block_on(self.inner.get_authenticated(url))
+    }
+}
+
+/// Blocking [`devicemgmt::get_services`].
+pub fn get_services(
+    client: &Client,
+    request: &devicemgmt::GetServices,
+) -> Result<devicemgmt::GetServicesResponse, Error> {
+    client.block_on(devicemgmt::get_services(client.as_async(), request))
+}
+
+/// Blocking [`devicemgmt::get_device_information`].
+pub fn get_device_information(
+    client: &Client,
+    request: &devicemgmt::GetDeviceInformation,
+) -> Result<devicemgmt::GetDeviceInformationResponse, Error> {
+    client.block_on(devicemgmt::get_device_information(
+        client.as_async(),
+        request,
+    ))
+}
+
+/// Blocking [`media::get_profiles`].
+pub fn get_profiles(
+    client: &Client,
+    request: &media::GetProfiles,
+) -> Result<media::GetProfilesResponse, Error> {
+    client.block_on(media::get_profiles(client.as_async(), request))
+}
+
+/// Blocking [`media::get_stream_uri`].
+pub fn get_stream_uri(
+    client: &Client,
+    request: &media::GetStreamUri,
+) -> Result<media::GetStreamUriResponse, Error> {
+    client.block_on(media::get_stream_uri(client.as_async(), request))
+}
+
+#[test]
+fn test_blocking_request() {
+    // Nothing listens on the port once the listener is dropped.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let uri = Url::parse(&format!("http://{}/onvif/device_service", addr)).unwrap();
+
+    let client = ClientBuilder::new(&uri).build().unwrap();
+    let result = get_device_information(&client, &Default::default());
+
+    assert!(matches!(result, Err(Error::Connection(_))));
+    assert_eq!(client.stats().requests, 1);
+}
@@ -4,10 +4,51 @@ pub mod client;
 mod tests;
 
 use auth::username_token::UsernameToken;
-use schema::soap_envelope;
-use xmltree::{Element, Namespace, XMLNode};
+use schema::soap_envelope::{
+    self, Fault, Faultcode, FaultcodeEnum, Faultreason, Reasontext, Subcode,
+};
+use std::collections::{HashMap, HashSet};
+use xmltree::{Element, EmitterConfig, Namespace, XMLNode};
 
 const SOAP_URI: &str = "http://www.w3.org/2003/05/soap-envelope";
+const SOAP_11_URI: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const WS_ADDRESSING_URI: &str = "http://www.w3.org/2005/08/addressing";
+const WS_ADDRESSING_ANONYMOUS: &str = "http://www.w3.org/2005/08/addressing/anonymous";
+const EVENTS_URI: &str = "http://www.onvif.org/ver10/events/wsdl";
+const NOTIFICATION_URI: &str = "http://docs.oasis-open.org/wsn/b-2";
+
+/// Version of the SOAP protocol used for requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoapVersion {
+    /// SOAP 1.1, which is the only version some legacy devices understand.
+    V11,
+    /// SOAP 1.2, which is required by ONVIF.
+    V12,
+}
+
+impl Default for SoapVersion {
+    fn default() -> Self {
+        Self::V12
+    }
+}
+
+impl SoapVersion {
+    /// Namespace of the envelope.
+    pub fn namespace(self) -> &'static str {
+        match self {
+            Self::V11 => SOAP_11_URI,
+            Self::V12 => SOAP_URI,
+        }
+    }
+
+    /// Value of the HTTP `Content-Type` header.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::V11 => "text/xml; charset=utf-8",
+            Self::V12 => "application/soap+xml; charset=utf-8;",
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum Error {
@@ -17,6 +58,9 @@ pub enum Error {
     BodyIsEmpty,
     Fault(Box<soap_envelope::Fault>),
     InternalError(String),
+    /// The namespace prefixes map gives the same prefix to several namespaces, or renames a
+    /// prefix which appears in a value.
+    PrefixConflict(String),
 }
 
 #[derive(Debug)]
@@ -24,11 +68,57 @@ pub struct Response {
     pub response: Option<String>,
 }
 
-pub fn soap(xml: &str, username_token: &Option<UsernameToken>) -> Result<String, Error> {
+/// What precedes the root element of the request envelopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlProlog {
+    /// `<?xml version="1.0" encoding="UTF-8"?>`, which devices expect most often.
+    Declaration,
+    /// UTF-8 byte order mark followed by the declaration.
+    BomAndDeclaration,
+    /// Nothing, the document starts with the envelope.
+    None,
+}
+
+impl Default for XmlProlog {
+    fn default() -> Self {
+        Self::Declaration
+    }
+}
+
+impl XmlProlog {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Declaration => r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            Self::BomAndDeclaration => "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+            Self::None => "",
+        }
+    }
+}
+
+/// Wraps `xml` into a SOAP envelope.
+///
+/// If `to` is set, the envelope gets WS-Addressing headers: a new `MessageID`, `to` as the `To`,
+/// the [`action`] of `xml` and an anonymous `ReplyTo`. `namespace_prefixes` maps namespace URIs
+/// to the prefixes which must be used for them in the resulting envelope. Namespaces which are not
+/// in the map keep their original prefixes, unless the map gives these prefixes to other
+/// namespaces: then they get new `nsN` prefixes.
+///
+/// Only the names of elements are renamed, so a prefix which also appears in a value, like
+/// `xsi:type="tt:Duration"` or a topic expression, can't be: [`Error::PrefixConflict`] is returned
+/// instead of a request with an undeclared prefix.
+pub fn soap(
+    xml: &str,
+    username_token: &Option<UsernameToken>,
+    to: Option<&str>,
+    namespace_prefixes: &HashMap<String, String>,
+    soap_version: SoapVersion,
+    prolog: XmlProlog,
+) -> Result<String, Error> {
     let app_data = parse(xml)?;
+    let action = action(&app_data);
 
     let mut namespaces = app_data.namespaces.clone().unwrap_or_else(Namespace::empty);
-    namespaces.put("s", SOAP_URI);
+    namespaces.put("s", soap_version.namespace());
 
     let mut body = Element::new("Body");
     body.prefix = Some("s".to_string());
@@ -38,18 +128,107 @@ pub fn soap(xml: &str, username_token: &Option<UsernameToken>) -> Result<String,
     envelope.namespaces = Some(namespaces);
     envelope.prefix = Some("s".to_string());
 
+    let mut header = Element::new("Header");
+    header.prefix = Some("s".to_string());
+
+    if let Some(to) = to {
+        let message_id = format!("urn:uuid:{}", uuid::Uuid::new_v4());
+        header
+            .children
+            .push(ws_addressing_header("MessageID", &message_id));
+        header.children.push(ws_addressing_header("To", to));
+        if let Some(action) = action {
+            header
+                .children
+                .push(ws_addressing_header("Action", &action));
+        }
+
+        let mut address = Element::new("Address");
+        address.prefix = Some("wsa".to_string());
+        address.namespace = Some(WS_ADDRESSING_URI.to_string());
+        address
+            .children
+            .push(XMLNode::Text(WS_ADDRESSING_ANONYMOUS.to_string()));
+
+        let mut reply_to = ws_addressing_element("ReplyTo");
+        reply_to.children.push(XMLNode::Element(address));
+        header.children.push(XMLNode::Element(reply_to));
+    }
+
     if let Some(username_token) = username_token {
-        let mut header = Element::new("Header");
-        header.prefix = Some("s".to_string());
         header
             .children
             .push(XMLNode::Element(parse(&username_token.to_xml())?));
+    }
+
+    if !header.children.is_empty() {
         envelope.children.push(XMLNode::Element(header));
     }
 
     envelope.children.push(XMLNode::Element(body));
 
-    xml_element_to_string(&envelope)
+    if !namespace_prefixes.is_empty() {
+        let namespace_prefixes = resolve_prefixes(&envelope, namespace_prefixes)?;
+        check_prefix_values(&envelope, &namespace_prefixes)?;
+        rename_prefixes(&mut envelope, &namespace_prefixes);
+    }
+
+    // Write the prolog ourselves as strict devices expect its exact form.
+    let mut out = prolog.as_str().as_bytes().to_vec();
+    envelope
+        .write_with_config(
+            &mut out,
+            EmitterConfig::new().write_document_declaration(false),
+        )
+        .map_err(|_| Error::InternalError("Could not write XML element".to_string()))?;
+    String::from_utf8(out).map_err(|e| Error::InternalError(e.to_string()))
+}
+
+/// WS-Addressing `Action` of the operation whose request is `element`.
+///
+/// The WSDLs of the events service declare explicit actions, for the rest of the services the
+/// action is the namespace of the request followed by its name.
+pub fn action(element: &Element) -> Option<String> {
+    let namespace = element.namespace.as_deref()?;
+    let name = element.name.as_str();
+
+    Some(match (namespace, name) {
+        (EVENTS_URI, "PullMessages" | "Seek" | "SetSynchronizationPoint") => {
+            format!("{}/PullPointSubscription/{}Request", namespace, name)
+        }
+        (EVENTS_URI, _) => format!("{}/EventPortType/{}Request", namespace, name),
+        (NOTIFICATION_URI, "Renew" | "Unsubscribe") => format!(
+            "http://docs.oasis-open.org/wsn/bw-2/SubscriptionManager/{}Request",
+            name
+        ),
+        (NOTIFICATION_URI, _) => format!(
+            "http://docs.oasis-open.org/wsn/bw-2/NotificationProducer/{}Request",
+            name
+        ),
+        _ => format!("{}/{}", namespace, name),
+    })
+}
+
+/// [`action`] of the request `xml`, e.g. for the `SOAPAction` HTTP header of SOAP 1.1.
+pub fn request_action(xml: &str) -> Option<String> {
+    action(&parse(xml).ok()?)
+}
+
+fn ws_addressing_element(name: &str) -> Element {
+    let mut namespaces = Namespace::empty();
+    namespaces.put("wsa", WS_ADDRESSING_URI);
+
+    let mut element = Element::new(name);
+    element.prefix = Some("wsa".to_string());
+    element.namespace = Some(WS_ADDRESSING_URI.to_string());
+    element.namespaces = Some(namespaces);
+    element
+}
+
+fn ws_addressing_header(name: &str, text: &str) -> XMLNode {
+    let mut element = ws_addressing_element(name);
+    element.children.push(XMLNode::Text(text.to_string()));
+    XMLNode::Element(element)
 }
 
 pub fn unsoap(xml: &str) -> Result<String, Error> {
@@ -62,7 +241,11 @@ pub fn unsoap(xml: &str) -> Result<String, Error> {
     let body = root.get_child("Body").ok_or(Error::BodyNotFound)?;
 
     if let Some(fault) = body.get_child("Fault") {
-        let fault = deserialize_fault(fault)?;
+        let fault = if root.namespace.as_deref() == Some(SOAP_11_URI) {
+            convert_soap_11_fault(fault)
+        } else {
+            deserialize_fault(fault)?
+        };
         return Err(Error::Fault(Box::new(fault)));
     }
 
@@ -75,6 +258,144 @@ pub fn unsoap(xml: &str) -> Result<String, Error> {
         .ok_or(Error::BodyIsEmpty)?
 }
 
+/// Adds new prefixes to `namespace_prefixes` for the namespaces of `element` whose prefixes the
+/// map gives to other namespaces, so that no prefix ends up bound to two namespaces.
+fn resolve_prefixes(
+    element: &Element,
+    namespace_prefixes: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, Error> {
+    let mut owners = HashMap::new();
+    for (uri, prefix) in namespace_prefixes {
+        if let Some(other) = owners.insert(prefix.as_str(), uri.as_str()) {
+            return Err(Error::PrefixConflict(format!(
+                "Prefix {} is set for both {} and {}",
+                prefix, other, uri
+            )));
+        }
+    }
+
+    let mut bindings = vec![];
+    collect_bindings(element, &mut bindings);
+
+    let mut used: HashSet<String> = bindings
+        .iter()
+        .map(|(prefix, _)| prefix.clone())
+        .chain(namespace_prefixes.values().cloned())
+        .collect();
+    let mut resolved = namespace_prefixes.clone();
+
+    for (prefix, uri) in bindings {
+        let taken = owners
+            .get(prefix.as_str())
+            .map_or(false, |owner| *owner != uri);
+
+        if taken && !resolved.contains_key(&uri) {
+            let fresh = (0..)
+                .map(|i| format!("ns{}", i))
+                .find(|prefix| !used.contains(prefix))
+                .unwrap();
+            used.insert(fresh.clone());
+            resolved.insert(uri, fresh);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Prefix bindings declared in `element` and its descendants, except the reserved ones.
+fn collect_bindings(element: &Element, bindings: &mut Vec<(String, String)>) {
+    if let Some(namespaces) = &element.namespaces {
+        for (prefix, uri) in namespaces {
+            if !uri.is_empty() && prefix != "xml" && prefix != "xmlns" {
+                bindings.push((prefix.to_string(), uri.to_string()));
+            }
+        }
+    }
+
+    for child in &element.children {
+        if let XMLNode::Element(child) = child {
+            collect_bindings(child, bindings);
+        }
+    }
+}
+
+/// Fails if a prefix which `namespace_prefixes` renames appears in an attribute value or a text of
+/// `element` or its descendants, where it would be left as is.
+fn check_prefix_values(
+    element: &Element,
+    namespace_prefixes: &HashMap<String, String>,
+) -> Result<(), Error> {
+    if let Some(namespaces) = &element.namespaces {
+        let texts = element.children.iter().filter_map(|child| match child {
+            XMLNode::Text(text) => Some(text.as_str()),
+            _ => None,
+        });
+
+        for value in element.attributes.values().map(String::as_str).chain(texts) {
+            for (prefix, uri) in namespaces {
+                let renamed = namespace_prefixes
+                    .get(uri)
+                    .map_or(false, |renamed| renamed != prefix);
+
+                if renamed && !prefix.is_empty() && refers_to_prefix(value, prefix) {
+                    return Err(Error::PrefixConflict(format!(
+                        "Prefix {} can't be renamed as the value {} refers to it",
+                        prefix, value
+                    )));
+                }
+            }
+        }
+    }
+
+    element.children.iter().try_for_each(|child| match child {
+        XMLNode::Element(child) => check_prefix_values(child, namespace_prefixes),
+        _ => Ok(()),
+    })
+}
+
+/// Whether `value` has a qualified name with `prefix`, i.e. `prefix:` which isn't the end of a
+/// longer name.
+fn refers_to_prefix(value: &str, prefix: &str) -> bool {
+    let qualified = format!("{}:", prefix);
+
+    value.match_indices(&qualified).any(|(i, _)| {
+        value[..i]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !(c.is_alphanumeric() || "-_.".contains(c)))
+    })
+}
+
+fn rename_prefixes(element: &mut Element, namespace_prefixes: &HashMap<String, String>) {
+    let uri = element.namespace.clone().or_else(|| {
+        let prefix = element.prefix.as_deref()?;
+        element
+            .namespaces
+            .as_ref()?
+            .get(prefix)
+            .map(|uri| uri.to_string())
+    });
+
+    if let Some(prefix) = uri.and_then(|uri| namespace_prefixes.get(&uri)) {
+        element.prefix = Some(prefix.clone());
+    }
+
+    if let Some(namespaces) = element.namespaces.as_mut() {
+        let mut renamed = Namespace::empty();
+        for (prefix, uri) in &*namespaces {
+            let prefix = namespace_prefixes.get(uri).map_or(prefix, String::as_str);
+            renamed.put(prefix, uri);
+        }
+        *namespaces = renamed;
+    }
+
+    for child in element.children.iter_mut() {
+        if let XMLNode::Element(child) = child {
+            rename_prefixes(child, namespace_prefixes);
+        }
+    }
+}
+
 fn parse(xml: &str) -> Result<Element, Error> {
     Element::parse(xml.as_bytes()).map_err(|_| Error::ParseError)
 }
@@ -90,3 +411,48 @@ fn deserialize_fault(envelope: &Element) -> Result<soap_envelope::Fault, Error>
     let string = xml_element_to_string(envelope)?;
     yaserde::de::from_str(&string).map_err(Error::InternalError)
 }
+
+/// Converts a SOAP 1.1 fault, which has unqualified `faultcode`, `faultstring`, `faultactor` and
+/// `detail` children, to the SOAP 1.2 structure.
+fn convert_soap_11_fault(fault: &Element) -> Fault {
+    let text = |name: &str| {
+        fault
+            .get_child(name)
+            .and_then(|child| child.get_text())
+            .map(|text| text.trim().to_string())
+    };
+
+    let reason = text("faultstring").map(|text| Reasontext {
+        lang: fault
+            .get_child("faultstring")
+            .and_then(|faultstring| faultstring.attributes.get("lang"))
+            .cloned()
+            .unwrap_or_default(),
+        text,
+    });
+
+    let code = text("faultcode").unwrap_or_default();
+
+    // SOAP 1.1 has no subcodes, so devices put ONVIF codes like `ter:NotAuthorized` right into
+    // `faultcode`. Keep them as subcodes so the faults are handled the same way for both versions.
+    let local_code = code.rsplit(':').next().unwrap_or_default();
+    let subcode = match local_code {
+        "VersionMismatch" | "MustUnderstand" | "Client" | "Server" => None,
+        _ => Some(Subcode {
+            value: code.clone(),
+        }),
+    };
+
+    Fault {
+        code: Faultcode {
+            value: FaultcodeEnum(code),
+            subcode,
+        },
+        reason: Faultreason {
+            text: reason.into_iter().collect(),
+        },
+        node: None,
+        role: text("faultactor"),
+        detail: None,
+    }
+}
@@ -24,7 +24,15 @@ fn test_soap() {
         </s:Envelope>
         "#;
 
-    let actual = soap(app_data, &None).unwrap();
+    let actual = soap(
+        app_data,
+        &None,
+        None,
+        &Default::default(),
+        SoapVersion::V12,
+        XmlProlog::Declaration,
+    )
+    .unwrap();
 
     println!("{}", actual);
     println!("{}", expected);
@@ -32,6 +40,320 @@ fn test_soap() {
     assert_xml_eq(actual.as_str(), expected);
 }
 
+#[test]
+fn test_soap_namespace_prefixes() {
+    let app_data = r#"
+        <my:Book xmlns:my="http://www.example.my/schema">
+            <my:Title>Such book</my:Title>
+        </my:Book>
+        "#;
+
+    let expected = r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope"
+                      xmlns:bk="http://www.example.my/schema">
+            <env:Body>
+                <bk:Book>
+                    <bk:Title>Such book</bk:Title>
+                </bk:Book>
+            </env:Body>
+        </env:Envelope>
+        "#;
+
+    let prefixes = vec![
+        (SOAP_URI.to_string(), "env".to_string()),
+        ("http://www.example.my/schema".to_string(), "bk".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    let actual = soap(
+        app_data,
+        &None,
+        None,
+        &prefixes,
+        SoapVersion::V12,
+        XmlProlog::Declaration,
+    )
+    .unwrap();
+
+    println!("{}", actual);
+
+    assert_xml_eq(actual.as_str(), expected);
+}
+
+#[test]
+fn test_soap_namespace_prefix_collision() {
+    let app_data = r#"
+        <my:Book xmlns:my="http://www.example.my/schema">
+            <my:Title>Such book</my:Title>
+        </my:Book>
+        "#;
+
+    // The envelope namespace has to give up its `s` prefix.
+    let expected = r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <ns0:Envelope xmlns:ns0="http://www.w3.org/2003/05/soap-envelope"
+                      xmlns:s="http://www.example.my/schema">
+            <ns0:Body>
+                <s:Book>
+                    <s:Title>Such book</s:Title>
+                </s:Book>
+            </ns0:Body>
+        </ns0:Envelope>
+        "#;
+
+    let envelope = |prefixes: Vec<(&str, &str)>| {
+        let prefixes = prefixes
+            .into_iter()
+            .map(|(uri, prefix)| (uri.to_string(), prefix.to_string()))
+            .collect();
+
+        soap(
+            app_data,
+            &None,
+            None,
+            &prefixes,
+            SoapVersion::V12,
+            XmlProlog::Declaration,
+        )
+    };
+
+    let actual = envelope(vec![("http://www.example.my/schema", "s")]).unwrap();
+
+    println!("{}", actual);
+
+    assert_xml_eq(actual.as_str(), expected);
+
+    assert!(matches!(
+        envelope(vec![
+            (SOAP_URI, "bk"),
+            ("http://www.example.my/schema", "bk")
+        ]),
+        Err(Error::PrefixConflict(_))
+    ));
+}
+
+#[test]
+fn test_soap_namespace_prefix_in_value() {
+    let envelope = |app_data: &str| {
+        let prefixes = vec![("http://www.example.my/schema".to_string(), "bk".to_string())]
+            .into_iter()
+            .collect();
+
+        soap(
+            app_data,
+            &None,
+            None,
+            &prefixes,
+            SoapVersion::V12,
+            XmlProlog::Declaration,
+        )
+    };
+
+    // Renaming `my` would leave the values with an undeclared prefix.
+    assert!(matches!(
+        envelope(
+            r#"<my:Book xmlns:my="http://www.example.my/schema"
+                        xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+                        xsi:type="my:Novel"/>"#
+        ),
+        Err(Error::PrefixConflict(_))
+    ));
+    assert!(matches!(
+        envelope(
+            r#"<my:Book xmlns:my="http://www.example.my/schema">
+                <my:Genre>my:Novel|my:Poetry</my:Genre>
+            </my:Book>"#
+        ),
+        Err(Error::PrefixConflict(_))
+    ));
+
+    // Neither a longer name nor a prefix which isn't renamed is a conflict.
+    assert!(envelope(
+        r#"<my:Book xmlns:my="http://www.example.my/schema"
+                    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+                    xsi:type="xsi:anyType">
+            <my:Title>Enemy: a story</my:Title>
+        </my:Book>"#
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_soap_prolog() {
+    let app_data = r#"<my:Book xmlns:my="http://www.example.my/schema"/>"#;
+
+    let envelope = |prolog| {
+        soap(
+            app_data,
+            &None,
+            None,
+            &Default::default(),
+            SoapVersion::V12,
+            prolog,
+        )
+        .unwrap()
+    };
+
+    assert!(envelope(XmlProlog::Declaration)
+        .starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><s:Envelope"#));
+    assert!(envelope(XmlProlog::BomAndDeclaration)
+        .starts_with("\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?><s:Envelope"));
+    assert!(envelope(XmlProlog::None).starts_with("<s:Envelope"));
+}
+
+#[test]
+fn test_soap_11() {
+    let app_data = r#"
+        <my:Book xmlns:my="http://www.example.my/schema">
+            <my:Title>Such book</my:Title>
+        </my:Book>
+        "#;
+
+    let expected = r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"
+                    xmlns:my="http://www.example.my/schema">
+            <s:Body>
+                <my:Book>
+                    <my:Title>Such book</my:Title>
+                </my:Book>
+            </s:Body>
+        </s:Envelope>
+        "#;
+
+    let actual = soap(
+        app_data,
+        &None,
+        None,
+        &Default::default(),
+        SoapVersion::V11,
+        XmlProlog::Declaration,
+    )
+    .unwrap();
+
+    println!("{}", actual);
+
+    assert_xml_eq(actual.as_str(), expected);
+}
+
+#[test]
+fn test_soap_ws_addressing() {
+    let app_data = r#"<tev:PullMessages xmlns:tev="http://www.onvif.org/ver10/events/wsdl"/>"#;
+    let to = "http://192.168.0.2/onvif/Subscription?Idx=1&a=b";
+
+    let envelopes: Vec<_> = (0..2)
+        .map(|_| {
+            soap(
+                app_data,
+                &None,
+                Some(to),
+                &Default::default(),
+                SoapVersion::V12,
+                XmlProlog::Declaration,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    println!("{}", envelopes[0]);
+
+    let headers: Vec<_> = envelopes
+        .iter()
+        .map(|envelope| {
+            Element::parse(envelope.as_bytes())
+                .unwrap()
+                .get_child("Header")
+                .unwrap()
+                .clone()
+        })
+        .collect();
+
+    let header_text = |header: &Element, name: &str| {
+        let element = header.get_child(name).unwrap();
+        assert_eq!(element.namespace.as_deref(), Some(WS_ADDRESSING_URI));
+        element.get_text().unwrap().into_owned()
+    };
+
+    let message_id = header_text(&headers[0], "MessageID");
+    assert!(message_id.starts_with("urn:uuid:"));
+    assert_ne!(message_id, header_text(&headers[1], "MessageID"));
+
+    assert_eq!(header_text(&headers[0], "To"), to);
+    assert_eq!(
+        header_text(&headers[0], "Action"),
+        "http://www.onvif.org/ver10/events/wsdl/PullPointSubscription/PullMessagesRequest"
+    );
+    assert_eq!(
+        header_text(headers[0].get_child("ReplyTo").unwrap(), "Address"),
+        "http://www.w3.org/2005/08/addressing/anonymous"
+    );
+}
+
+#[test]
+fn test_action() {
+    let action_of = |xml: &str| action(&Element::parse(xml.as_bytes()).unwrap());
+
+    assert_eq!(
+        action_of(
+            r#"<tds:GetDeviceInformation xmlns:tds="http://www.onvif.org/ver10/device/wsdl"/>"#
+        )
+        .as_deref(),
+        Some("http://www.onvif.org/ver10/device/wsdl/GetDeviceInformation")
+    );
+    assert_eq!(
+        action_of(r#"<wsnt:Renew xmlns:wsnt="http://docs.oasis-open.org/wsn/b-2"/>"#).as_deref(),
+        Some("http://docs.oasis-open.org/wsn/bw-2/SubscriptionManager/RenewRequest")
+    );
+    assert_eq!(action_of("<GetDeviceInformation/>"), None);
+}
+
+#[test]
+fn test_request_action() {
+    assert_eq!(
+        request_action(r#"<trt:GetProfiles xmlns:trt="http://www.onvif.org/ver10/media/wsdl"/>"#)
+            .as_deref(),
+        Some("http://www.onvif.org/ver10/media/wsdl/GetProfiles")
+    );
+    assert_eq!(request_action("<GetProfiles/>"), None);
+    assert_eq!(request_action("not xml"), None);
+}
+
+#[test]
+fn test_unsoap_11_fault() {
+    let input = r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/"
+                           xmlns:ter="http://www.onvif.org/ver10/error">
+            <SOAP-ENV:Body>
+                <SOAP-ENV:Fault>
+                    <faultcode>ter:NotAuthorized</faultcode>
+                    <faultstring>Sender not authorized</faultstring>
+                </SOAP-ENV:Fault>
+            </SOAP-ENV:Body>
+        </SOAP-ENV:Envelope>
+        "#;
+
+    match unsoap(input) {
+        Err(Error::Fault(fault)) => {
+            assert_eq!(
+                fault.code.value,
+                FaultcodeEnum("ter:NotAuthorized".to_string())
+            );
+            assert!(fault.is_unauthorized());
+            assert_eq!(
+                fault.reason.text,
+                vec![Reasontext {
+                    lang: String::new(),
+                    text: "Sender not authorized".to_string()
+                }]
+            );
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
 #[test]
 fn test_unsoap() {
     #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
@@ -115,10 +437,12 @@ fn test_get_fault() {
         fault.reason.text,
         vec![
             Reasontext {
-                lang: "en".to_string()
+                lang: "en".to_string(),
+                text: "fault reason 1".to_string()
             },
             Reasontext {
-                lang: "en".to_string()
+                lang: "en".to_string(),
+                text: "fault reason 2".to_string()
             }
         ]
     );
@@ -0,0 +1,12 @@
+use schema::onvif as tt;
+
+/// Converts an ONVIF date and time (in UTC) to chrono. Returns `None` for invalid dates.
+pub fn to_chrono(date_time: &tt::DateTime) -> Option<chrono::DateTime<chrono::Utc>> {
+    let date = &date_time.date;
+    let time = &date_time.time;
+
+    let naive = chrono::NaiveDate::from_ymd_opt(date.year, date.month as u32, date.day as u32)?
+        .and_hms_opt(time.hour as u32, time.minute as u32, time.second as u32)?;
+
+    Some(chrono::DateTime::from_utc(naive, chrono::Utc))
+}
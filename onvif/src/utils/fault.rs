@@ -0,0 +1,105 @@
+use schema::transport;
+
+/// Local name of the subcode of a SOAP fault, e.g. `InvalidArgVal` for `ter:InvalidArgVal`.
+fn fault_subcode(e: &transport::Error) -> Option<&str> {
+    match e {
+        transport::Error::Fault {
+            subcode: Some(subcode),
+            ..
+        } => subcode.rsplit(':').next(),
+        _ => None,
+    }
+}
+
+/// Whether the device responded with a fault meaning the requested operation or setting isn't
+/// supported (`ter:ActionNotSupported`, `ter:NotSupported`, ...).
+pub fn is_not_supported(e: &transport::Error) -> bool {
+    fault_subcode(e).map_or(false, |subcode| subcode.ends_with("NotSupported"))
+}
+
+/// Whether the device responded with a fault meaning an argument of the request is invalid, e.g.
+/// out of its range (`ter:InvalidArgVal`, `ter:InvalidArgs`).
+pub fn is_invalid_argument(e: &transport::Error) -> bool {
+    fault_subcode(e).map_or(false, |subcode| subcode.starts_with("InvalidArg"))
+}
+
+/// Whether the device responded with a `ter:Action` fault, meaning it couldn't carry out a valid
+/// request in its current state, e.g. `GotoHomePosition` without a home position. The reason is in
+/// a nested subcode, which isn't kept.
+pub fn is_action_failed(e: &transport::Error) -> bool {
+    fault_subcode(e) == Some("Action")
+}
+
+/// Whether the connection was closed or reset before the response was read, as devices do when
+/// they apply a change which takes them offline (a reboot, a new IP address).
+pub fn is_connection_dropped(e: &transport::Error) -> bool {
+    matches!(e, transport::Error::ConnectionDropped(_))
+}
+
+#[cfg(test)]
+fn fault(code: &str, subcode: Option<&str>) -> transport::Error {
+    transport::Error::Fault {
+        code: code.to_string(),
+        subcode: subcode.map(str::to_string),
+        reason: None,
+    }
+}
+
+#[test]
+fn not_supported_faults() {
+    assert!(is_not_supported(&fault(
+        "env:Receiver",
+        Some("ter:ActionNotSupported")
+    )));
+    assert!(is_not_supported(&fault(
+        "env:Sender",
+        Some("ter:NotSupported")
+    )));
+    assert!(!is_not_supported(&fault(
+        "env:Sender",
+        Some("ter:InvalidArgVal")
+    )));
+    assert!(!is_not_supported(&fault("env:Receiver", None)));
+    assert!(!is_not_supported(&transport::Error::Protocol(
+        "SOAP fault env:Receiver/ter:ActionNotSupported".to_string()
+    )));
+}
+
+#[test]
+fn invalid_argument_faults() {
+    assert!(is_invalid_argument(&fault(
+        "env:Sender",
+        Some("ter:InvalidArgVal")
+    )));
+    assert!(is_invalid_argument(&fault(
+        "env:Sender",
+        Some("InvalidArgs")
+    )));
+    assert!(!is_invalid_argument(&fault(
+        "env:Receiver",
+        Some("ter:ActionNotSupported")
+    )));
+}
+
+#[test]
+fn action_faults() {
+    assert!(is_action_failed(&fault("env:Receiver", Some("ter:Action"))));
+    assert!(!is_action_failed(&fault(
+        "env:Receiver",
+        Some("ter:ActionNotSupported")
+    )));
+    assert!(!is_action_failed(&fault("env:Receiver", None)));
+}
+
+#[test]
+fn dropped_connections() {
+    assert!(is_connection_dropped(&transport::Error::ConnectionDropped(
+        "connection closed before message completed".to_string()
+    )));
+    assert!(!is_connection_dropped(&transport::Error::Connection(
+        "tcp connect error: Connection refused (os error 111)".to_string()
+    )));
+    assert!(!is_connection_dropped(&transport::Error::Other(
+        "Connection reset by peer (os error 104)".to_string()
+    )));
+}
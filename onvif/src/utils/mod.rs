@@ -1,4 +1,9 @@
+pub mod date_time;
 pub mod display_list;
+pub mod fault;
 pub mod hash;
 #[cfg(test)]
+pub mod recording_transport;
+pub mod redact;
+#[cfg(test)]
 pub mod xml_eq;
@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use schema::transport::{self, Transport};
+use std::sync::Mutex;
+use xmltree::Element;
+
+/// Transport which records the requests and responds to all of them the same way, or per
+/// operation.
+pub struct RecordingTransport {
+    response: Response,
+    requests: Mutex<Vec<String>>,
+}
+
+enum Response {
+    All(&'static str),
+    PerOperation(Vec<(&'static str, &'static str)>),
+    Error(fn(String) -> transport::Error, &'static str),
+}
+
+impl RecordingTransport {
+    pub fn new(response: &'static str) -> Self {
+        Self::with_response(Response::All(response))
+    }
+
+    /// Responds to each request with the response of its operation, given as pairs like
+    /// `("GetProfile", "<trt:GetProfileResponse ...")`. The other operations fail with a
+    /// `ter:ActionNotSupported` fault.
+    pub fn per_operation(responses: &[(&'static str, &'static str)]) -> Self {
+        Self::with_response(Response::PerOperation(responses.to_vec()))
+    }
+
+    /// Responds with a [`transport::Error::Fault`] with the `code/subcode` of `fault`, e.g.
+    /// `env:Sender/ter:InvalidArgVal`.
+    pub fn fault(fault: &'static str) -> Self {
+        Self::error(parse_fault, fault)
+    }
+
+    /// Fails all the requests with `error(message)`, e.g. a
+    /// [`transport::Error::ConnectionDropped`].
+    pub fn error(error: fn(String) -> transport::Error, message: &'static str) -> Self {
+        Self::with_response(Response::Error(error, message))
+    }
+
+    fn with_response(response: Response) -> Self {
+        Self {
+            response,
+            requests: Default::default(),
+        }
+    }
+
+    pub fn last_request(&self) -> Element {
+        let requests = self.requests.lock().unwrap();
+        Element::parse(requests.last().unwrap().as_bytes()).unwrap()
+    }
+
+    /// All the requests, in the order they were sent.
+    pub fn requests(&self) -> Vec<Element> {
+        let requests = self.requests.lock().unwrap();
+        requests
+            .iter()
+            .map(|request| Element::parse(request.as_bytes()).unwrap())
+            .collect()
+    }
+}
+
+fn parse_fault(fault: String) -> transport::Error {
+    let mut parts = fault.splitn(2, '/');
+    transport::Error::Fault {
+        code: parts.next().unwrap_or_default().to_string(),
+        subcode: parts.next().map(str::to_string),
+        reason: None,
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn request(&self, message: &str) -> Result<String, transport::Error> {
+        self.requests.lock().unwrap().push(message.to_string());
+
+        match &self.response {
+            Response::All(response) => Ok(response.to_string()),
+            Response::PerOperation(responses) => {
+                let operation = Element::parse(message.as_bytes()).unwrap().name;
+                responses
+                    .iter()
+                    .find(|(name, _)| *name == operation)
+                    .map(|(_, response)| response.to_string())
+                    .ok_or_else(|| parse_fault("env:Receiver/ter:ActionNotSupported".to_string()))
+            }
+            Response::Error(error, message) => Err(error(message.to_string())),
+        }
+    }
+}
@@ -1,10 +1,8 @@
 use super::*;
 
-use assert_approx_eq::assert_approx_eq;
 use async_trait::async_trait;
 use onvif as tt;
 use utils::assert_xml_eq;
-use xsd_types::types as xs;
 
 mod utils;
 
@@ -286,10 +284,7 @@ fn duration_serialization() {
         uri: "http://a/b/c".to_string(),
         invalid_after_connect: false,
         invalid_after_reboot: true,
-        timeout: xs::Duration {
-            seconds: 60.0,
-            ..Default::default()
-        },
+        timeout: std::time::Duration::from_secs(60).into(),
     };
 
     let expected = r#"
@@ -298,7 +293,7 @@ fn duration_serialization() {
             <tt:Uri>http://a/b/c</tt:Uri>
             <tt:InvalidAfterConnect>false</tt:InvalidAfterConnect>
             <tt:InvalidAfterReboot>true</tt:InvalidAfterReboot>
-            <tt:Timeout>PT60S</tt:Timeout>
+            <tt:Timeout>PT1M</tt:Timeout>
         </tt:MediaUri>
         "#;
 
@@ -323,7 +318,45 @@ fn duration_deserialization() {
     assert_eq!(des.uri, "http://a/b/c".to_string());
     assert!(!des.invalid_after_connect);
     assert!(des.invalid_after_reboot);
-    assert_approx_eq!(des.timeout.seconds, 60.0);
+    assert_eq!(des.timeout.duration, std::time::Duration::from_secs(60));
+}
+
+#[test]
+fn duration_parsing() {
+    use std::time::Duration;
+
+    let parse = |s: &str| s.parse::<common::Duration>().unwrap();
+
+    assert_eq!(parse("PT30S").duration, Duration::from_secs(30));
+    assert_eq!(parse("P1DT2H").duration, Duration::from_secs(26 * 3600));
+    assert_eq!(parse("P1Y2M").duration, Duration::from_secs(425 * 86400));
+    assert_eq!(parse("PT1.25S").duration, Duration::from_millis(1250));
+    assert_eq!(parse("PT0.000000001S").duration, Duration::from_nanos(1));
+
+    let negative = parse("-PT5M");
+    assert!(negative.negative);
+    assert_eq!(negative.duration, Duration::from_secs(300));
+    assert!(Duration::try_from(negative).is_err());
+    assert!(!parse("-PT0S").negative);
+
+    for invalid in [
+        "", "P", "PT", "30S", "PT1D", "P1H", "P1.5D", "PT-1S", "PT1M5", "PTS",
+    ] {
+        assert!(invalid.parse::<common::Duration>().is_err(), "{}", invalid);
+    }
+
+    for (duration, formatted) in [
+        (Duration::ZERO, "PT0S"),
+        (Duration::from_secs(30), "PT30S"),
+        (Duration::from_secs(26 * 3600), "P1DT2H"),
+        (Duration::from_secs(86400), "P1D"),
+        (Duration::from_millis(90_500), "PT1M30.5S"),
+    ] {
+        assert_eq!(common::Duration::from(duration).to_string(), formatted);
+        assert_eq!(parse(formatted).duration, duration);
+    }
+
+    assert_eq!(negative.to_string(), "-PT5M");
 }
 
 #[tokio::test]
@@ -693,3 +726,27 @@ fn media2_configs_name_serialization() {
         type_of(&media2::GetAudioDecoderConfigurationOptions::default())
     );
 }
+
+#[test]
+fn stream_setup_serialization() {
+    let expected = r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <tt:StreamSetup xmlns:tt="http://www.onvif.org/ver10/schema">
+            <tt:Stream>RTP-Unicast</tt:Stream>
+            <tt:Transport>
+                <tt:Protocol>HTTP</tt:Protocol>
+            </tt:Transport>
+        </tt:StreamSetup>
+        "#;
+
+    let actual = yaserde::ser::to_string(&tt::StreamSetup::rtsp_over_http()).unwrap();
+
+    assert_xml_eq(actual.as_str(), expected);
+
+    let multicast = tt::StreamSetup::rtp_multicast();
+    assert_eq!(multicast.stream, tt::StreamType::RtpMulticast);
+    assert_eq!(
+        multicast.transport,
+        tt::Transport::new(tt::TransportProtocol::Udp)
+    );
+}
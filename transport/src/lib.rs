@@ -1,4 +1,7 @@
 use async_trait::async_trait;
+use bytes::Bytes;
+use futures_core::Stream;
+use std::pin::Pin;
 use thiserror::Error;
 use yaserde::{YaDeserialize, YaSerialize};
 
@@ -6,31 +9,106 @@ use yaserde::{YaDeserialize, YaSerialize};
 pub enum Error {
     #[error("Serialization failed: {0}")]
     Serialization(String),
-    #[error("Deserialization failed: {0}")]
-    Deserialization(String),
+    #[error("Deserialization failed: {source} (response: {body_snippet:?})")]
+    Deserialization {
+        /// Beginning of the response which couldn't be deserialized, decoded lossily.
+        body_snippet: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The request couldn't be authorized, e.g. the Digest challenge of the device is invalid.
     #[error("Authorization failed: {0}")]
     Authorization(String),
+    /// The device rejected the credentials, with an HTTP 401 or a `ter:NotAuthorized` fault.
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
     #[error("Redirection error: {0}")]
     Redirection(String),
+    #[error("Too many redirects: {0}")]
+    TooManyRedirects(String),
+    /// The device couldn't be reached, e.g. the connection was refused or the host is unknown.
     #[error("Connection error: {0}")]
     Connection(String),
+    /// The connection was closed or reset after it was established, before the response was
+    /// complete.
+    #[error("Connection dropped: {0}")]
+    ConnectionDropped(String),
+    /// The device responded with an HTTP error status and without a SOAP fault.
+    #[error("HTTP error: {0}")]
+    Http(http::StatusCode),
     #[error("Timeout occurred: {0}")]
     Timeout(String),
+    /// The device responded with a SOAP fault, other than a `ter:NotAuthorized` one which is
+    /// reported as [`Error::Unauthorized`].
+    #[error(
+        "SOAP fault {code}{}",
+        .subcode.as_ref().map_or_else(String::new, |subcode| format!("/{}", subcode))
+    )]
+    Fault {
+        /// Code of the fault, e.g. `env:Sender`.
+        code: String,
+        /// First subcode of the fault, e.g. `ter:InvalidArgVal`.
+        subcode: Option<String>,
+        /// Explanation of the fault, if the device gives one.
+        reason: Option<String>,
+    },
     #[error("Protocol error: {0}")]
     Protocol(String),
     #[error("Other: {0}")]
     Other(String),
 }
 
+/// Maximum number of characters of a response kept in [`Error::Deserialization`].
+pub const BODY_SNIPPET_LEN: usize = 256;
+
+impl Error {
+    /// Creates an [`Error::Deserialization`] with a snippet of the response `body`.
+    pub fn deserialization(
+        body: impl AsRef<[u8]>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        let body = String::from_utf8_lossy(body.as_ref());
+        let mut body_snippet: String = body.chars().take(BODY_SNIPPET_LEN).collect();
+        if body_snippet.len() < body.len() {
+            body_snippet.push_str("...");
+        }
+
+        Error::Deserialization {
+            body_snippet,
+            source: source.into(),
+        }
+    }
+}
+
 impl From<Error> for String {
     fn from(error: Error) -> String {
         error.to_string()
     }
 }
 
+/// Response XML as it's received, see [`Transport::request_stream`].
+pub type ResponseStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
 #[async_trait]
 pub trait Transport {
     async fn request(&self, message: &str) -> Result<String, Error>;
+
+    /// Sends the request like [`Transport::request`], but yields the response in chunks as they
+    /// are received instead of all at once, so that large responses can be parsed incrementally.
+    ///
+    /// The chunks make up either the response element alone or the whole SOAP envelope. Errors
+    /// which come before the response (HTTP errors, faults with an error status) are returned
+    /// as by `request`. The default implementation yields the result of `request` at once.
+    async fn request_stream(&self, message: &str) -> Result<ResponseStream, Error> {
+        Ok(buffered(self.request(message).await?))
+    }
+}
+
+/// Stream yielding the whole `response` as a single chunk, for the transports which don't receive
+/// responses incrementally.
+pub fn buffered(response: String) -> ResponseStream {
+    Box::pin(futures_util::stream::once(async move {
+        Ok(Bytes::from(response))
+    }))
 }
 
 pub async fn request<T: Transport, R: YaSerialize, S: YaDeserialize>(
@@ -39,13 +117,37 @@ pub async fn request<T: Transport, R: YaSerialize, S: YaDeserialize>(
 ) -> Result<S, Error> {
     let ser = |obj: &R| yaserde::ser::to_string(obj).map_err(Error::Serialization);
 
-    let de = |s: &str| yaserde::de::from_str(s).map_err(Error::Deserialization);
+    let de = |s: &str| yaserde::de::from_str(s).map_err(|e| Error::deserialization(s, e));
 
     de(&transport
         .request(&crop_xml_declaration(&ser(request)?))
         .await?)
 }
 
+/// Serializes the request and returns the response without deserializing it.
+///
+/// Useful when the response has to be inspected before (or instead of) mapping it to a type,
+/// e.g. to recover from a single malformed element in an otherwise valid response.
+pub async fn request_raw<T: Transport, R: YaSerialize>(
+    transport: &T,
+    request: &R,
+) -> Result<String, Error> {
+    let ser = yaserde::ser::to_string(request).map_err(Error::Serialization)?;
+
+    transport.request(&crop_xml_declaration(&ser)).await
+}
+
+/// Serializes the request and returns the response as it's received, see
+/// [`Transport::request_stream`].
+pub async fn request_stream<T: Transport + Sync, R: YaSerialize>(
+    transport: &T,
+    request: &R,
+) -> Result<ResponseStream, Error> {
+    let ser = yaserde::ser::to_string(request).map_err(Error::Serialization)?;
+
+    transport.request_stream(&crop_xml_declaration(&ser)).await
+}
+
 fn crop_xml_declaration(xml: &str) -> String {
     xml.split("?>").skip(1).collect()
 }
@@ -57,3 +159,26 @@ fn test_crop_xml_declaration() {
         "<element />"
     );
 }
+
+#[test]
+fn test_deserialization_body_snippet() {
+    let body = format!("<a>{}\u{fffd}", "x".repeat(BODY_SNIPPET_LEN));
+
+    match Error::deserialization(body.as_bytes(), "unexpected end") {
+        Error::Deserialization {
+            body_snippet,
+            source,
+        } => {
+            assert_eq!(body_snippet.chars().count(), BODY_SNIPPET_LEN + 3);
+            assert!(body_snippet.starts_with("<a>xxx"));
+            assert!(body_snippet.ends_with("..."));
+            assert_eq!(source.to_string(), "unexpected end");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    match Error::deserialization(b"<a>\xff</a>", "invalid") {
+        Error::Deserialization { body_snippet, .. } => assert_eq!(body_snippet, "<a>\u{fffd}</a>"),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
@@ -1091,11 +1091,11 @@ pub mod ftp_content_configuration {
 pub struct FtpContentConfigurationUploadImages {
     // Upload Image action; how long?
     #[yaserde(prefix = "tae", rename = "HowLong")]
-    pub how_long: xs::Duration,
+    pub how_long: common::Duration,
 
     // Upload Image action; sample interval?
     #[yaserde(prefix = "tae", rename = "SampleInterval")]
-    pub sample_interval: xs::Duration,
+    pub sample_interval: common::Duration,
 
     // Upload Image action; name of destination file
     #[yaserde(prefix = "tae", rename = "FileName")]
@@ -1172,15 +1172,15 @@ impl Validate for Smsmessage {}
 pub struct TriggeredRecordingConfiguration {
     // Length of recording time before the triggering event
     #[yaserde(prefix = "tae", rename = "PreRecordDuration")]
-    pub pre_record_duration: xs::Duration,
+    pub pre_record_duration: common::Duration,
 
     // Recording after alarm recording duration
     #[yaserde(prefix = "tae", rename = "PostRecordDuration")]
-    pub post_record_duration: xs::Duration,
+    pub post_record_duration: common::Duration,
 
     // Record duration
     #[yaserde(prefix = "tae", rename = "RecordDuration")]
-    pub record_duration: xs::Duration,
+    pub record_duration: common::Duration,
 
     // Recording frame rate
     #[yaserde(prefix = "tae", rename = "RecordFrameRate")]
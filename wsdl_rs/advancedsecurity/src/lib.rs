@@ -996,7 +996,7 @@ pub struct CreateRSAKeyPairResponse {
 
     // Best-effort estimate of how long the key generation will take.
     #[yaserde(prefix = "tas", rename = "EstimatedCreationTime")]
-    pub estimated_creation_time: xs::Duration,
+    pub estimated_creation_time: common::Duration,
 }
 
 impl Validate for CreateRSAKeyPairResponse {}
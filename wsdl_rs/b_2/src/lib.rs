@@ -160,7 +160,7 @@ impl Validate for Notify {}
 #[derive(PartialEq, Debug, UtilsUnionSerDe)]
 pub enum AbsoluteOrRelativeTimeType {
     DateTime(xs::DateTime),
-    Duration(xs::Duration),
+    Duration(common::Duration),
     __Unknown__(String),
 }
 
@@ -78,7 +78,7 @@ pub struct ServiceCapabilities {
     // The time period is defined as an [ISO 8601] duration string (e.g.
     // “PT5M”).
     #[yaserde(attribute, rename = "DefaultCredentialSuspensionDuration")]
-    pub default_credential_suspension_duration: Option<xs::Duration>,
+    pub default_credential_suspension_duration: Option<common::Duration>,
 }
 
 impl Validate for ServiceCapabilities {}
@@ -723,7 +723,7 @@ pub struct SendReceiveSerialCommand {
     // Indicates that the command should be responded back within the specified
     // period of time.
     #[yaserde(prefix = "tmd", rename = "TimeOut")]
-    pub time_out: Option<xs::Duration>,
+    pub time_out: Option<common::Duration>,
 
     // This element may be put in the case that data length returned from the
     // connected serial device is already determined as some fixed bytes length.
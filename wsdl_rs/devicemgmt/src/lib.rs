@@ -1264,7 +1264,7 @@ pub struct SetDynamicDNS {
 
     // DNS record time to live.
     #[yaserde(prefix = "tds", rename = "TTL")]
-    pub ttl: Option<xs::Duration>,
+    pub ttl: Option<common::Duration>,
 }
 
 impl Validate for SetDynamicDNS {}
@@ -2261,10 +2261,10 @@ pub struct StartFirmwareUpgradeResponse {
     pub upload_uri: String,
 
     #[yaserde(prefix = "tds", rename = "UploadDelay")]
-    pub upload_delay: xs::Duration,
+    pub upload_delay: common::Duration,
 
     #[yaserde(prefix = "tds", rename = "ExpectedDownTime")]
-    pub expected_down_time: xs::Duration,
+    pub expected_down_time: common::Duration,
 }
 
 impl Validate for StartFirmwareUpgradeResponse {}
@@ -2288,7 +2288,7 @@ pub struct StartSystemRestoreResponse {
     pub upload_uri: String,
 
     #[yaserde(prefix = "tds", rename = "ExpectedDownTime")]
-    pub expected_down_time: xs::Duration,
+    pub expected_down_time: common::Duration,
 }
 
 impl Validate for StartSystemRestoreResponse {}
@@ -2,7 +2,6 @@
 
 use types as pt;
 use validate::Validate;
-use xsd_types::types as xs;
 use yaserde_derive::{YaDeserialize, YaSerialize};
 
 // ServiceCapabilities structure reflects optional functionality of a service.
@@ -118,13 +117,13 @@ pub struct Timings {
     // ReleaseTime is the time from when the latch is unlocked until it is
     // relocked again (unless the door is physically opened).
     #[yaserde(prefix = "tdc", rename = "ReleaseTime")]
-    pub release_time: xs::Duration,
+    pub release_time: common::Duration,
 
     // The time from when the door is physically opened until the door is set in
     // the
     // DoorOpenTooLong alarm state.
     #[yaserde(prefix = "tdc", rename = "OpenTime")]
-    pub open_time: xs::Duration,
+    pub open_time: common::Duration,
 
     // Some individuals need extra time to open the door before the latch
     // relocks.
@@ -132,20 +131,20 @@ pub struct Timings {
     // UseExtendedTime
     // is set to true in the AccessDoor command.
     #[yaserde(prefix = "tdc", rename = "ExtendedReleaseTime")]
-    pub extended_release_time: Option<xs::Duration>,
+    pub extended_release_time: Option<common::Duration>,
 
     // If the door is physically opened after access is granted,
     // then DelayTimeBeforeRelock is the time from when the door is physically
     // opened until the latch goes back to locked state.
     #[yaserde(prefix = "tdc", rename = "DelayTimeBeforeRelock")]
-    pub delay_time_before_relock: Option<xs::Duration>,
+    pub delay_time_before_relock: Option<common::Duration>,
 
     // Some individuals need extra time to pass through the door. If supported,
     // ExtendedOpenTime shall be added to OpenTime if UseExtendedTime is set to
     // true
     // in the AccessDoor command.
     #[yaserde(prefix = "tdc", rename = "ExtendedOpenTime")]
-    pub extended_open_time: Option<xs::Duration>,
+    pub extended_open_time: Option<common::Duration>,
 
     // Before a DoorOpenTooLong alarm state is generated, a signal will sound to
     // indicate
@@ -153,7 +152,7 @@ pub struct Timings {
     // DoorOpenTooLong
     // the warning signal shall sound.
     #[yaserde(prefix = "tdc", rename = "PreAlarmTime")]
-    pub pre_alarm_time: Option<xs::Duration>,
+    pub pre_alarm_time: Option<common::Duration>,
 
     #[yaserde(prefix = "tdc", rename = "Extension")]
     pub extension: Option<TimingsExtension>,
@@ -821,15 +820,15 @@ pub struct AccessDoor {
 
     // Optional - overrides ReleaseTime if specified.
     #[yaserde(prefix = "tdc", rename = "AccessTime")]
-    pub access_time: Option<xs::Duration>,
+    pub access_time: Option<common::Duration>,
 
     // Optional - overrides OpenTime if specified.
     #[yaserde(prefix = "tdc", rename = "OpenTooLongTime")]
-    pub open_too_long_time: Option<xs::Duration>,
+    pub open_too_long_time: Option<common::Duration>,
 
     // Optional - overrides PreAlarmTime if specified.
     #[yaserde(prefix = "tdc", rename = "PreAlarmTime")]
-    pub pre_alarm_time: Option<xs::Duration>,
+    pub pre_alarm_time: Option<common::Duration>,
 
     // Future extension.
     #[yaserde(prefix = "tdc", rename = "Extension")]
@@ -131,7 +131,7 @@ impl Validate for CreatePullPointSubscriptionResponse {}
 pub struct PullMessages {
     // Maximum time to block until this method returns.
     #[yaserde(prefix = "tev", rename = "Timeout")]
-    pub timeout: xs::Duration,
+    pub timeout: common::Duration,
 
     // Upper limit for the number of messages to return at once. A server
     // implementation may decide to return less messages.
@@ -172,7 +172,7 @@ impl Validate for PullMessagesResponse {}
 pub struct PullMessagesFaultResponse {
     // Maximum timeout supported by the device.
     #[yaserde(prefix = "tev", rename = "MaxTimeout")]
-    pub max_timeout: xs::Duration,
+    pub max_timeout: common::Duration,
 
     // Maximum message limit supported by the device.
     #[yaserde(prefix = "tev", rename = "MaxMessageLimit")]
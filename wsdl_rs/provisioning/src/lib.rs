@@ -188,7 +188,7 @@ impl Validate for SourceCapabilities {}
 pub struct Capabilities {
     // Maximum time before stopping movement after a move operation.
     #[yaserde(prefix = "tpv", rename = "DefaultTimeout")]
-    pub default_timeout: xs::Duration,
+    pub default_timeout: common::Duration,
 
     // Capabilities per video source.
     #[yaserde(prefix = "tpv", rename = "Source")]
@@ -236,7 +236,7 @@ pub struct PanMove {
 
     // "Operation timeout, if less than default timeout.
     #[yaserde(prefix = "tpv", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for PanMove {}
@@ -266,7 +266,7 @@ pub struct TiltMove {
 
     // "Operation timeout, if less than default timeout.
     #[yaserde(prefix = "tpv", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for TiltMove {}
@@ -296,7 +296,7 @@ pub struct ZoomMove {
 
     // "Operation timeout, if less than default timeout.
     #[yaserde(prefix = "tpv", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for ZoomMove {}
@@ -326,7 +326,7 @@ pub struct RollMove {
 
     // "Operation timeout, if less than default timeout.
     #[yaserde(prefix = "tpv", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for RollMove {}
@@ -356,7 +356,7 @@ pub struct FocusMove {
 
     // "Operation timeout, if less than default timeout.
     #[yaserde(prefix = "tpv", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for FocusMove {}
@@ -2,7 +2,6 @@
 
 use onvif as tt;
 use validate::Validate;
-use xsd_types::types as xs;
 use yaserde_derive::{YaDeserialize, YaSerialize};
 
 #[derive(Default, PartialEq, Debug, YaSerialize, YaDeserialize)]
@@ -444,7 +443,7 @@ pub struct ContinuousMove {
 
     // An optional Timeout parameter.
     #[yaserde(prefix = "tptz", rename = "Timeout")]
-    pub timeout: Option<xs::Duration>,
+    pub timeout: Option<common::Duration>,
 }
 
 impl Validate for ContinuousMove {}
@@ -138,7 +138,7 @@ pub struct FindRecordings {
     // and subsequent requests. A device shall support at least values up to ten
     // seconds.
     #[yaserde(prefix = "tse", rename = "KeepAliveTime")]
-    pub keep_alive_time: xs::Duration,
+    pub keep_alive_time: common::Duration,
 }
 
 impl Validate for FindRecordings {}
@@ -176,7 +176,7 @@ pub struct GetRecordingSearchResults {
     // The maximum time before responding to the request, even if the MinResults
     // parameter is not fulfilled.
     #[yaserde(prefix = "tse", rename = "WaitTime")]
-    pub wait_time: Option<xs::Duration>,
+    pub wait_time: Option<common::Duration>,
 }
 
 impl Validate for GetRecordingSearchResults {}
@@ -231,7 +231,7 @@ pub struct FindEvents {
     // and subsequent requests. A device shall support at least values up to ten
     // seconds.
     #[yaserde(prefix = "tse", rename = "KeepAliveTime")]
-    pub keep_alive_time: xs::Duration,
+    pub keep_alive_time: common::Duration,
 }
 
 impl Validate for FindEvents {}
@@ -270,7 +270,7 @@ pub struct GetEventSearchResults {
     // The maximum time before responding to the request, even if the MinResults
     // parameter is not fulfilled.
     #[yaserde(prefix = "tse", rename = "WaitTime")]
-    pub wait_time: Option<xs::Duration>,
+    pub wait_time: Option<common::Duration>,
 }
 
 impl Validate for GetEventSearchResults {}
@@ -318,7 +318,7 @@ pub struct FindPTZPosition {
     // and subsequent requests. A device shall support at least values up to ten
     // seconds.
     #[yaserde(prefix = "tse", rename = "KeepAliveTime")]
-    pub keep_alive_time: xs::Duration,
+    pub keep_alive_time: common::Duration,
 }
 
 impl Validate for FindPTZPosition {}
@@ -357,7 +357,7 @@ pub struct GetPTZPositionSearchResults {
     // The maximum time before responding to the request, even if the MinResults
     // parameter is not fulfilled.
     #[yaserde(prefix = "tse", rename = "WaitTime")]
-    pub wait_time: Option<xs::Duration>,
+    pub wait_time: Option<common::Duration>,
 }
 
 impl Validate for GetPTZPositionSearchResults {}
@@ -405,7 +405,7 @@ pub struct FindMetadata {
     // and subsequent requests. A device shall support at least values up to ten
     // seconds.
     #[yaserde(prefix = "tse", rename = "KeepAliveTime")]
-    pub keep_alive_time: xs::Duration,
+    pub keep_alive_time: common::Duration,
 }
 
 impl Validate for FindMetadata {}
@@ -444,7 +444,7 @@ pub struct GetMetadataSearchResults {
     // The maximum time before responding to the request, even if the MinResults
     // parameter is not fulfilled.
     #[yaserde(prefix = "tse", rename = "WaitTime")]
-    pub wait_time: Option<xs::Duration>,
+    pub wait_time: Option<common::Duration>,
 }
 
 impl Validate for GetMetadataSearchResults {}
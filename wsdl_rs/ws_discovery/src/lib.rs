@@ -10,6 +10,9 @@ pub mod probe {
     pub struct Probe {
         #[yaserde(prefix = "d", rename = "Types")]
         pub types: String,
+
+        #[yaserde(prefix = "d", rename = "Scopes")]
+        pub scopes: Option<String>,
     }
 
     #[derive(Default, Eq, PartialEq, Debug, YaSerialize)]
@@ -45,7 +48,9 @@ pub mod probe {
         prefix = "s",
         namespace = "s: http://www.w3.org/2003/05/soap-envelope",
         namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery",
-        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing"
+        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing",
+        namespace = "dn: http://www.onvif.org/ver10/network/wsdl",
+        namespace = "tds: http://www.onvif.org/ver10/device/wsdl"
     )]
     pub struct Envelope {
         #[yaserde(prefix = "s", rename = "Header")]
@@ -62,12 +67,27 @@ pub mod probe_matches {
     use url::Url;
     use yaserde_derive::YaDeserialize;
 
+    #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "w",
+        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing"
+    )]
+    pub struct EndpointReference {
+        /// Stable identifier of the device, usually a `urn:uuid:` URN.
+        #[yaserde(prefix = "w", rename = "Address")]
+        pub address: String,
+    }
+
     #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
     #[yaserde(
         prefix = "d",
-        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery"
+        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery",
+        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing"
     )]
     pub struct ProbeMatch {
+        #[yaserde(prefix = "w", rename = "EndpointReference")]
+        pub endpoint_reference: Option<EndpointReference>,
+
         #[yaserde(prefix = "d", rename = "Types")]
         pub types: String,
 
@@ -141,6 +161,15 @@ pub mod probe_matches {
             self.find_in_scopes("onvif://www.onvif.org/hardware/")
         }
 
+        pub fn location(&self) -> Option<String> {
+            self.find_in_scopes("onvif://www.onvif.org/location/")
+        }
+
+        /// ONVIF profiles the device claims to support, like `Streaming`, `S` or `T`.
+        pub fn profiles(&self) -> Vec<String> {
+            self.find_all_in_scopes("onvif://www.onvif.org/Profile/")
+        }
+
         pub fn find_in_scopes(&self, prefix: &str) -> Option<String> {
             self.scopes().iter().find_map(|url| {
                 url.as_str()
@@ -149,6 +178,17 @@ pub mod probe_matches {
             })
         }
 
+        pub fn find_all_in_scopes(&self, prefix: &str) -> Vec<String> {
+            self.scopes()
+                .iter()
+                .filter_map(|url| {
+                    url.as_str()
+                        .strip_prefix(prefix)
+                        .map(|s| percent_decode_str(s).decode_utf8_lossy().to_string())
+                })
+                .collect()
+        }
+
         fn split_string_to_urls(s: &str) -> Vec<Url> {
             s.split_whitespace()
                 .filter_map(|addr| Url::parse(addr).ok())
@@ -161,8 +201,12 @@ pub mod probe_matches {
         let ser = r#"
         <?xml version="1.0" encoding="utf-8"?>
         <wsd:ProbeMatch xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery"
+                        xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing"
                         xmlns:dn="http://www.onvif.org/ver10/network/wsdl"
                         xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+            <wsa:EndpointReference>
+                <wsa:Address>urn:uuid:c5a3a28d-b8aa-4b3a-9e55-3f7f2c3a0a1f</wsa:Address>
+            </wsa:EndpointReference>
             <wsd:Types>
                 dn:NetworkVideoTransmitter
                 tds:Device
@@ -187,6 +231,14 @@ pub mod probe_matches {
 
         assert_eq!(de.name(), Some("My Camera 2000".to_string()));
         assert_eq!(de.hardware(), Some("My-HW-2000".to_string()));
+        assert_eq!(
+            de.endpoint_reference.as_ref().unwrap().address,
+            "urn:uuid:c5a3a28d-b8aa-4b3a-9e55-3f7f2c3a0a1f"
+        );
+        assert_eq!(
+            de.profiles(),
+            vec!["G".to_string(), "Streaming".to_string()]
+        );
         assert!(de
             .find_in_scopes("onvif://www.onvif.org/type/video_encoder")
             .is_some());
@@ -202,3 +254,107 @@ pub mod probe_matches {
         );
     }
 }
+
+/// `Hello` and `Bye` messages which devices multicast when they join or leave the network.
+pub mod announcement {
+
+    use super::probe_matches::ProbeMatch;
+    use yaserde_derive::YaDeserialize;
+
+    #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "d",
+        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery"
+    )]
+    pub struct AppSequence {
+        /// Incremented each time the device restarts.
+        #[yaserde(attribute, rename = "InstanceId")]
+        pub instance_id: u64,
+
+        #[yaserde(attribute, rename = "SequenceId")]
+        pub sequence_id: Option<String>,
+
+        /// Incremented with each message sent by the device within an instance.
+        #[yaserde(attribute, rename = "MessageNumber")]
+        pub message_number: u64,
+    }
+
+    #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "s",
+        namespace = "s: http://www.w3.org/2003/05/soap-envelope",
+        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing",
+        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery"
+    )]
+    pub struct Header {
+        #[yaserde(prefix = "w", rename = "Action")]
+        pub action: String,
+
+        #[yaserde(prefix = "d", rename = "AppSequence")]
+        pub app_sequence: Option<AppSequence>,
+    }
+
+    /// `Hello` and `Bye` have the same content as `ProbeMatch`, except that `Bye` usually carries
+    /// only the endpoint reference.
+    #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "s",
+        namespace = "s: http://www.w3.org/2003/05/soap-envelope",
+        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery"
+    )]
+    pub struct Body {
+        #[yaserde(prefix = "d", rename = "Hello")]
+        pub hello: Option<ProbeMatch>,
+
+        #[yaserde(prefix = "d", rename = "Bye")]
+        pub bye: Option<ProbeMatch>,
+    }
+
+    #[derive(Default, Eq, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(prefix = "s", namespace = "s: http://www.w3.org/2003/05/soap-envelope")]
+    pub struct Envelope {
+        #[yaserde(prefix = "s", rename = "Header")]
+        pub header: Header,
+
+        #[yaserde(prefix = "s", rename = "Body")]
+        pub body: Body,
+    }
+
+    #[test]
+    fn hello() {
+        let ser = r#"
+        <?xml version="1.0" encoding="utf-8"?>
+        <s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"
+                    xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+                    xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery">
+            <s:Header>
+                <a:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Hello</a:Action>
+                <a:MessageID>uuid:1c8e5e06-7dec-11d0-c360-F01234567890</a:MessageID>
+                <d:AppSequence InstanceId="1638" MessageNumber="3"/>
+            </s:Header>
+            <s:Body>
+                <d:Hello>
+                    <d:Scopes>onvif://www.onvif.org/name/MyCamera2000</d:Scopes>
+                    <d:XAddrs>http://192.168.0.100/onvif/device_service</d:XAddrs>
+                </d:Hello>
+            </s:Body>
+        </s:Envelope>
+        "#;
+
+        let de: Envelope = yaserde::de::from_str(ser).unwrap();
+
+        assert_eq!(
+            de.header.app_sequence,
+            Some(AppSequence {
+                instance_id: 1638,
+                sequence_id: None,
+                message_number: 3,
+            })
+        );
+        assert_eq!(
+            de.body.hello.unwrap().name(),
+            Some("MyCamera2000".to_string())
+        );
+        assert!(de.body.bye.is_none());
+    }
+}
@@ -379,3 +379,161 @@ pub struct LocationEntity {
 }
 
 impl Validate for LocationEntity {}
+
+// Value of xsd:duration, e.g. PT30S or -P1DT2H.
+// Unlike xs::Duration, it converts to and from std::time::Duration. Years and months have no
+// fixed length, they're counted as 365 and 30 days. Fractions of seconds are kept with
+// nanosecond precision.
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy, UtilsDefaultSerde)]
+pub struct Duration {
+    // Set only for durations which aren't zero.
+    pub negative: bool,
+    pub duration: std::time::Duration,
+}
+
+impl Duration {
+    const DESIGNATORS: [(char, u64); 6] = [
+        ('Y', 365 * 86_400),
+        ('M', 30 * 86_400),
+        ('D', 86_400),
+        ('H', 3_600),
+        ('M', 60),
+        ('S', 1),
+    ];
+}
+
+impl From<std::time::Duration> for Duration {
+    fn from(duration: std::time::Duration) -> Self {
+        Self {
+            negative: false,
+            duration,
+        }
+    }
+}
+
+impl TryFrom<Duration> for std::time::Duration {
+    type Error = String;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        if duration.negative {
+            return Err(format!("Negative duration: {}", duration));
+        }
+        Ok(duration.duration)
+    }
+}
+
+impl FromStr for Duration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid xsd:duration: {}", s);
+
+        let (negative, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+        let (date, time) = match rest.split_once('T') {
+            Some((_, "")) => return Err(invalid()),
+            Some((date, time)) => (date, time),
+            None => (rest, ""),
+        };
+
+        let mut nanos: u128 = 0;
+        let mut components = 0;
+
+        for (mut part, designators) in [
+            (date, &Self::DESIGNATORS[..3]),
+            (time, &Self::DESIGNATORS[3..]),
+        ] {
+            for &(designator, seconds) in designators {
+                let end = match part.find(designator) {
+                    Some(end) => end,
+                    None => continue,
+                };
+                let (value, fraction) = match part[..end].split_once('.') {
+                    // Only seconds may have a fraction.
+                    Some((value, fraction)) if designator == 'S' => (value, fraction),
+                    Some(_) => return Err(invalid()),
+                    None => (&part[..end], ""),
+                };
+                if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+
+                let value: u128 = value.parse().map_err(|_| invalid())?;
+                let fraction: u128 = format!("{:0<9.9}", fraction).parse().unwrap();
+                nanos = value
+                    .checked_mul(u128::from(seconds) * 1_000_000_000)
+                    .and_then(|value| nanos.checked_add(value + fraction))
+                    .ok_or_else(invalid)?;
+
+                components += 1;
+                part = &part[end + 1..];
+            }
+
+            if !part.is_empty() {
+                return Err(invalid());
+            }
+        }
+
+        if components == 0 {
+            return Err(invalid());
+        }
+
+        let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| invalid())?;
+        let duration = std::time::Duration::new(secs, (nanos % 1_000_000_000) as u32);
+
+        Ok(Self {
+            negative: negative && !duration.is_zero(),
+            duration,
+        })
+    }
+}
+
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.duration.as_secs();
+        let nanos = self.duration.subsec_nanos();
+        let (days, hours, minutes, seconds) = (
+            secs / 86_400,
+            secs % 86_400 / 3_600,
+            secs % 3_600 / 60,
+            secs % 60,
+        );
+
+        if self.negative && !self.duration.is_zero() {
+            write!(f, "-")?;
+        }
+        write!(f, "P")?;
+
+        if days > 0 {
+            write!(f, "{}D", days)?;
+            if secs % 86_400 == 0 && nanos == 0 {
+                return Ok(());
+            }
+        }
+
+        write!(f, "T")?;
+        if hours > 0 {
+            write!(f, "{}H", hours)?;
+        }
+        if minutes > 0 {
+            write!(f, "{}M", minutes)?;
+        }
+        if seconds > 0 || nanos > 0 || secs % 86_400 == 0 {
+            write!(f, "{}", seconds)?;
+            if nanos > 0 {
+                write!(f, ".{}", format!("{:09}", nanos).trim_end_matches('0'))?;
+            }
+            write!(f, "S")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Duration {}
@@ -99,10 +99,10 @@ impl Validate for FloatRange {}
 #[yaserde(prefix = "tt", namespace = "tt: http://www.onvif.org/ver10/schema")]
 pub struct DurationRange {
     #[yaserde(prefix = "tt", rename = "Min")]
-    pub min: xs::Duration,
+    pub min: common::Duration,
 
     #[yaserde(prefix = "tt", rename = "Max")]
-    pub max: xs::Duration,
+    pub max: common::Duration,
 }
 
 impl Validate for DurationRange {}
@@ -686,7 +686,7 @@ pub struct VideoEncoderConfiguration {
 
     // The rtsp session timeout for the related video stream
     #[yaserde(prefix = "tt", rename = "SessionTimeout")]
-    pub session_timeout: xs::Duration,
+    pub session_timeout: common::Duration,
 
     // A value of true indicates that frame rate is a fixed value rather than an
     // upper limit,
@@ -1313,7 +1313,7 @@ pub struct AudioEncoderConfiguration {
 
     // The rtsp session timeout for the related audio stream
     #[yaserde(prefix = "tt", rename = "SessionTimeout")]
-    pub session_timeout: xs::Duration,
+    pub session_timeout: common::Duration,
 
     // User readable name. Length up to 64 characters.
     #[yaserde(prefix = "tt", rename = "Name")]
@@ -1506,7 +1506,7 @@ pub struct MetadataConfiguration {
     // The rtsp session timeout for the related audio stream (when using Media2
     // Service, this value is deprecated and ignored)
     #[yaserde(prefix = "tt", rename = "SessionTimeout")]
-    pub session_timeout: xs::Duration,
+    pub session_timeout: common::Duration,
 
     #[yaserde(prefix = "tt", rename = "AnalyticsEngineConfiguration")]
     pub analytics_engine_configuration: Option<AnalyticsEngineConfiguration>,
@@ -2030,6 +2030,35 @@ pub struct StreamSetup {
     pub transport: Transport,
 }
 
+impl StreamSetup {
+    // RTP-Unicast over RTSP (RTP/RTSP/TCP), the setup every device supports.
+    pub fn rtsp_unicast() -> Self {
+        Self::unicast(TransportProtocol::Rtsp)
+    }
+
+    // RTP-Multicast over UDP, to the multicast address configured on the device.
+    pub fn rtp_multicast() -> Self {
+        Self {
+            stream: StreamType::RtpMulticast,
+            transport: Transport::new(TransportProtocol::Udp),
+        }
+    }
+
+    // RTP-Unicast over RTSP tunneled in HTTP (RTP/RTSP/HTTP/TCP). The tunnel is selected by the
+    // protocol itself, the `tunnel` of the transport stays empty.
+    pub fn rtsp_over_http() -> Self {
+        Self::unicast(TransportProtocol::Http)
+    }
+
+    // RTP-Unicast over the given protocol.
+    pub fn unicast(protocol: TransportProtocol) -> Self {
+        Self {
+            stream: StreamType::RtpUnicast,
+            transport: Transport::new(protocol),
+        }
+    }
+}
+
 impl Validate for StreamSetup {}
 
 #[derive(PartialEq, Debug, YaSerialize, YaDeserialize)]
@@ -2064,6 +2093,16 @@ pub struct Transport {
     pub tunnel: Vec<Transport>,
 }
 
+impl Transport {
+    // Transport over `protocol`, without further tunnel options.
+    pub fn new(protocol: TransportProtocol) -> Self {
+        Self {
+            protocol,
+            tunnel: vec![],
+        }
+    }
+}
+
 impl Validate for Transport {}
 
 #[derive(PartialEq, Debug, YaSerialize, YaDeserialize)]
@@ -2110,7 +2149,7 @@ pub struct MediaUri {
     // to indicate that this stream URI is indefinitely valid even if the
     // profile changes
     #[yaserde(prefix = "tt", rename = "Timeout")]
-    pub timeout: xs::Duration,
+    pub timeout: common::Duration,
 }
 
 impl Validate for MediaUri {}
@@ -2720,7 +2759,7 @@ pub struct DynamicDNSInformation {
 
     // Time to live.
     #[yaserde(prefix = "tt", rename = "TTL")]
-    pub ttl: Option<xs::Duration>,
+    pub ttl: Option<common::Duration>,
 
     #[yaserde(prefix = "tt", rename = "Extension")]
     pub extension: Option<DynamicDNSInformationExtension>,
@@ -4369,7 +4408,7 @@ pub struct RelayOutputSettings {
     // monostable mode. If the Mode field is set to bistable mode the value of
     // the parameter can be ignored.
     #[yaserde(prefix = "tt", rename = "DelayTime")]
-    pub delay_time: xs::Duration,
+    pub delay_time: common::Duration,
 
     // 'open' or 'closed'
     #[yaserde(prefix = "tt", rename = "IdleState")]
@@ -4577,7 +4616,7 @@ pub struct Ptzconfiguration {
     // If the PTZ Node supports continuous movements, it shall specify a default
     // timeout, after which the movement stops.
     #[yaserde(prefix = "tt", rename = "DefaultPTZTimeout")]
-    pub default_ptz_timeout: Option<xs::Duration>,
+    pub default_ptz_timeout: Option<common::Duration>,
 
     // The Pan/Tilt limits element should be present for a PTZ Node that
     // supports an absolute Pan/Tilt. If the element is present it signals the
@@ -5127,7 +5166,7 @@ pub struct PtzpresetTourSpot {
     // Optional parameter to specify time duration of staying on this tour
     // sport.
     #[yaserde(prefix = "tt", rename = "StayTime")]
-    pub stay_time: Option<xs::Duration>,
+    pub stay_time: Option<common::Duration>,
 
     #[yaserde(prefix = "tt", rename = "Extension")]
     pub extension: Option<PtzpresetTourSpotExtension>,
@@ -5218,7 +5257,7 @@ pub struct PtzpresetTourStartingCondition {
     // Optional parameter to specify how long time duration the preset tour is
     // recurred.
     #[yaserde(prefix = "tt", rename = "RecurringDuration")]
-    pub recurring_duration: Option<xs::Duration>,
+    pub recurring_duration: Option<common::Duration>,
 
     // Optional parameter to choose which direction the preset tour goes.
     // Forward shall be chosen in case it is omitted.
@@ -6159,7 +6198,7 @@ pub struct IrCutFilterAutoAdjustment {
     // Delay time of toggling Ir cut filter to on/off after crossing of the
     // boundary exposure levels.
     #[yaserde(prefix = "tt", rename = "ResponseTime")]
-    pub response_time: Option<xs::Duration>,
+    pub response_time: Option<common::Duration>,
 
     #[yaserde(prefix = "tt", rename = "Extension")]
     pub extension: Option<IrCutFilterAutoAdjustmentExtension>,
@@ -8164,7 +8203,7 @@ pub struct RecordingConfiguration {
     // delete
     // recordings to free up storage space for new recordings.
     #[yaserde(prefix = "tt", rename = "MaximumRetentionTime")]
-    pub maximum_retention_time: xs::Duration,
+    pub maximum_retention_time: common::Duration,
 }
 
 impl Validate for RecordingConfiguration {}
@@ -8449,7 +8488,7 @@ impl Validate for GetRecordingJobsResponseItem {}
 pub struct ReplayConfiguration {
     // The RTSP session timeout.
     #[yaserde(prefix = "tt", rename = "SessionTimeout")]
-    pub session_timeout: xs::Duration,
+    pub session_timeout: common::Duration,
 }
 
 impl Validate for ReplayConfiguration {}
@@ -2,7 +2,6 @@
 
 use onvif as tt;
 use validate::Validate;
-use xsd_types::types as xs;
 use yaserde_derive::{YaDeserialize, YaSerialize};
 
 #[derive(Default, PartialEq, Debug, YaSerialize, YaDeserialize)]
@@ -355,7 +354,7 @@ pub struct RadiometryTemperatureRuleConfig {
     // Indicates the time interval during which the rule condition shall be met
     // to trigger an event.
     #[yaserde(prefix = "ttr", rename = "ThresholdTime")]
-    pub threshold_time: xs::Duration,
+    pub threshold_time: common::Duration,
 
     // Indicates the width in Kelvin of the temerature hysteresis band to be
     // considered by the rule.
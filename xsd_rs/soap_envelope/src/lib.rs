@@ -101,7 +101,9 @@ impl Validate for Faultreason {}
 pub struct Reasontext {
     #[yaserde(attribute, prefix = "xml" rename = "lang")]
     pub lang: String,
-    // TODO: process the value of Reasontext too
+
+    #[yaserde(text)]
+    pub text: String,
 }
 
 impl Validate for Reasontext {}